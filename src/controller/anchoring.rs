@@ -0,0 +1,188 @@
+use std::sync::Mutex;
+
+use crossbeam_channel::{bounded, Sender};
+use keri::{
+    event::EventMessage,
+    event_message::{SignedEventMessage, SignedTransferableReceipt},
+    prefix::{BasicPrefix, IdentifierPrefix, SelfAddressingPrefix},
+};
+use teliox::event::Event;
+use thiserror::Error;
+
+use crate::error::Error;
+
+// Collects tel events of concurrent updates, so they can be anchored in KEL
+// in batches.
+//
+// Consistency model:
+// - Anchoring in KEL is serial, because every interaction event depends on
+//   the previous one. Thread which gets the anchoring lock anchors all events
+//   waiting in queue in one interaction event and hands the result to the
+//   threads which submitted them. Events queued while an anchoring is in
+//   progress wait for the next one, so a batch never grows after its
+//   interaction event is made.
+// - Events of one `anchor_all` call are queued at once and always end up in
+//   the same interaction event. There is no ordering between events of
+//   different threads within a batch, or fairness between threads.
+// - Batch succeeds or fails as a whole: if anchoring fails, every thread
+//   with an event in the batch gets the error and none of its events is in
+//   KEL, so all of them can be retried.
+// - Processing of tel events happens after anchoring and can run in
+//   parallel, because events of different vcs are independent. Tel event is
+//   visible in TEL only after its anchoring event is in KEL, but its
+//   anchoring event can be in KEL before the tel event is processed.
+//   Controller holds its commit lock over that gap, so `Snapshot` never sees
+//   one without the other.
+// - Queue lock is only taken for a moment, either on its own or inside the
+//   anchoring lock, never the other way around, so they can't deadlock.
+#[derive(Debug, Default)]
+pub struct AnchorQueue {
+    pending: Mutex<Vec<(Event, Sender<Result<SignedEventMessage, String>>)>>,
+    anchoring: Mutex<()>,
+}
+
+impl AnchorQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Anchors event, possibly together with events of other threads, using
+    // `anchor` function and returns anchoring event.
+    pub fn anchor<F>(&self, event: Event, anchor: F) -> Result<SignedEventMessage, Error>
+    where
+        F: Fn(&[Event]) -> Result<SignedEventMessage, Error>,
+    {
+        self.anchor_all(vec![event], anchor)
+    }
+
+    // Anchors all events in the same anchoring event, possibly together with
+    // events of other threads.
+    pub fn anchor_all<F>(&self, events: Vec<Event>, anchor: F) -> Result<SignedEventMessage, Error>
+    where
+        F: Fn(&[Event]) -> Result<SignedEventMessage, Error>,
+    {
+        let (sender, receiver) = bounded(events.len());
+        // Events are queued at once, so they are taken in the same batch.
+        self.pending
+            .lock()
+            .unwrap()
+            .extend(events.into_iter().map(|event| (event, sender.clone())));
+        drop(sender);
+        {
+            let _anchoring = self.anchoring.lock().unwrap();
+            let batch: Vec<_> = self.pending.lock().unwrap().drain(..).collect();
+            // Event could be already anchored by other thread.
+            if !batch.is_empty() {
+                let (events, senders): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+                let result = anchor(&events).map_err(|e| e.to_string());
+                for sender in senders {
+                    // Receiver is kept until result is received.
+                    let _ = sender.send(result.clone());
+                }
+            }
+        }
+        receiver
+            .recv()
+            .map_err(|_| Error::Generic("Anchoring result lost".into()))?
+            .map_err(Error::Generic)
+    }
+}
+
+// Result of checking the chain from vc tel event to keys which were current
+// when it was anchored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnchorProof {
+    pub tel_event_digest: SelfAddressingPrefix,
+    pub kel_event_sn: u64,
+    pub kel_event_digest: SelfAddressingPrefix,
+    // Position of tel event seal in data of KEL event, which anchors many
+    // tel events in batch.
+    pub seal_index: usize,
+    pub signing_keys: Vec<BasicPrefix>,
+}
+
+// KEL event anchoring vc issuance, with receipts of identifiers which
+// witnessed it. Receipts carry no timestamp, so time of issuance is
+// established by records of the witnesses, who can confirm when they
+// receipted the event.
+#[derive(Debug)]
+pub struct TimedProof {
+    pub anchoring_event: EventMessage,
+    pub receipts: Vec<SignedTransferableReceipt>,
+    // Distinct identifiers which receipted anchoring event, including
+    // non-transferable witnesses. Empty if event isn't receipted yet.
+    pub witnesses: Vec<IdentifierPrefix>,
+}
+
+// Hop of anchoring chain which couldn't be verified.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum AnchoringFailure {
+    #[error("missing tel event")]
+    MissingTelEvent,
+
+    #[error("missing kel event at sn {0}")]
+    MissingKelEvent(u64),
+
+    #[error("source seal digest doesn't match kel event at sn {0}")]
+    SourceSealMismatch(u64),
+
+    #[error("kel event at sn {0} doesn't contain seal of tel event")]
+    MissingEventSeal(u64),
+
+    #[error("kel event at sn {0} contains more than one seal of tel event")]
+    DuplicateEventSeal(u64),
+}
+
+#[test]
+pub fn test_anchor_batch() -> Result<(), Error> {
+    use crate::test_support::fresh_tel;
+    use std::{sync::Arc, thread, time::Duration};
+
+    let (tel, _seal, _dir) = fresh_tel();
+    let queue = Arc::new(AnchorQueue::new());
+    let batches = Arc::new(Mutex::new(vec![]));
+    let (started_sender, started) = bounded(1);
+    let (release, released) = bounded::<()>(1);
+
+    // First anchoring holds the anchoring lock until released.
+    let first = {
+        let (queue, batches) = (Arc::clone(&queue), Arc::clone(&batches));
+        let event = tel.make_issuance_event(b"vc0")?;
+        thread::spawn(move || {
+            queue.anchor(event, |events: &[Event]| {
+                started_sender.send(()).unwrap();
+                released.recv().unwrap();
+                batches.lock().unwrap().push(events.len());
+                Err(Error::Generic("Not anchored".into()))
+            })
+        })
+    };
+    started.recv().unwrap();
+
+    let waiting: Vec<_> = (1..3)
+        .map(|i| {
+            let (queue, batches) = (Arc::clone(&queue), Arc::clone(&batches));
+            let event = tel.make_issuance_event(format!("vc{}", i).as_bytes())?;
+            Ok(thread::spawn(move || {
+                queue.anchor(event, |events: &[Event]| {
+                    batches.lock().unwrap().push(events.len());
+                    Err(Error::Generic("Not anchored".into()))
+                })
+            }))
+        })
+        .collect::<Result<_, Error>>()?;
+    while queue.pending.lock().unwrap().len() < 2 {
+        thread::sleep(Duration::from_millis(1));
+    }
+    release.send(()).unwrap();
+
+    // Every thread gets the result of its batch.
+    assert!(first.join().unwrap().is_err());
+    for handle in waiting {
+        assert!(handle.join().unwrap().is_err());
+    }
+    // Both events queued during first anchoring are anchored together.
+    assert_eq!(*batches.lock().unwrap(), vec![1, 2]);
+
+    Ok(())
+}
@@ -0,0 +1,235 @@
+use std::{path::Path, sync::Arc};
+
+use keri::{
+    derivation::self_addressing::SelfAddressing,
+    event::SerializationFormats,
+    prefix::{BasicPrefix, IdentifierPrefix},
+    signer::KeyManager,
+};
+use tempfile::TempDir;
+
+use super::{
+    is_legacy_layout, resolve_db_paths, to_event_seal, to_source_seal, Controller, Dispatcher,
+    LAYOUT_MARKER,
+};
+use crate::{error::Error, kerl::KERL, tel::Tel};
+
+// Capacity of dispatcher task queue.
+pub const DEFAULT_QUEUE_CAPACITY: usize = 5;
+
+// Configuration of newly incepted controller. Defaults are the same as in
+// `Controller::init`.
+#[derive(Debug, Clone)]
+pub struct ControllerBuilder {
+    serialization: SerializationFormats,
+    derivation: SelfAddressing,
+    backers: Vec<IdentifierPrefix>,
+    backer_threshold: u64,
+    witnesses: Vec<BasicPrefix>,
+    witness_threshold: u64,
+    queue_capacity: usize,
+    durable: bool,
+    temporary: bool,
+}
+
+impl Default for ControllerBuilder {
+    fn default() -> Self {
+        Self {
+            serialization: SerializationFormats::JSON,
+            derivation: SelfAddressing::Blake3_256,
+            backers: vec![],
+            backer_threshold: 0,
+            witnesses: vec![],
+            witness_threshold: 0,
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            durable: false,
+            temporary: false,
+        }
+    }
+}
+
+impl ControllerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Serialization format of tel events. KEL events are always serialized
+    // as JSON.
+    pub fn serialization(mut self, format: SerializationFormats) -> Self {
+        self.serialization = format;
+        self
+    }
+
+    // Derivation of hashes of issued messages.
+    pub fn derivation(mut self, derivation: SelfAddressing) -> Self {
+        self.derivation = derivation;
+        self
+    }
+
+    // Backers set in registry inception event.
+    pub fn backers(mut self, backers: Vec<IdentifierPrefix>) -> Self {
+        self.backers = backers;
+        self
+    }
+
+    pub fn backer_threshold(mut self, threshold: u64) -> Self {
+        self.backer_threshold = threshold;
+        self
+    }
+
+    // Witnesses set in KEL inception event. Vcs are verified only when
+    // `threshold` of them receipted the anchoring event.
+    pub fn witnesses(mut self, witnesses: Vec<BasicPrefix>, threshold: u64) -> Self {
+        self.witnesses = witnesses;
+        self.witness_threshold = threshold;
+        self
+    }
+
+    // Capacity of task queue, used only by `build_dispatcher`.
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = capacity;
+        self
+    }
+
+    // Durable controller flushes its databases after every mutating
+    // operation, see `Controller::with_flush_every_write`.
+    pub fn durable(mut self, durable: bool) -> Self {
+        self.durable = durable;
+        self
+    }
+
+    // Temporary controller keeps its databases in temporary directory,
+    // removed when controller is dropped. `db_dir` given to `build` isn't
+    // used then.
+    pub fn temporary(mut self, temporary: bool) -> Self {
+        self.temporary = temporary;
+        self
+    }
+
+    pub fn build<K: KeyManager + Send + Sync>(
+        &self,
+        km: K,
+        db_dir: &Path,
+    ) -> Result<Controller<K>, Error> {
+        if self.temporary {
+            let temp_dir = TempDir::new()?;
+            let mut controller = self.incept(km, temp_dir.path())?;
+            controller.temp_dir = Some(temp_dir);
+            return Ok(controller);
+        }
+        self.incept(km, db_dir)
+    }
+
+    pub fn build_dispatcher<K: KeyManager + Send + Sync>(
+        &self,
+        km: K,
+        db_dir: &Path,
+    ) -> Result<Dispatcher<K>, Error> {
+        Ok(Dispatcher::with_queue(
+            self.build(km, db_dir)?,
+            self.queue_capacity,
+        ))
+    }
+
+    fn incept<K: KeyManager + Send + Sync>(
+        &self,
+        km: K,
+        db_dir_path: &Path,
+    ) -> Result<Controller<K>, Error> {
+        let (kel_db_path, tel_db_path) = resolve_db_paths(db_dir_path);
+        if KERL::is_incepted(&kel_db_path) || is_legacy_layout(db_dir_path) {
+            return Err(Error::Generic(
+                "Database directory already contains KEL, use load instead".into(),
+            ));
+        }
+        std::fs::create_dir_all(db_dir_path)?;
+        std::fs::File::create(db_dir_path.join(LAYOUT_MARKER))?;
+        let mut tel = Tel::new(tel_db_path.as_path())?.with_serialization(self.serialization);
+        let mut kerl = KERL::new(kel_db_path.as_path())?;
+        if self.witnesses.is_empty() {
+            kerl.incept(&km)?;
+        } else {
+            kerl.incept_with_witnesses(&km, &self.witnesses, self.witness_threshold)?;
+        }
+
+        let vcp = tel.make_inception_event(
+            kerl.get_prefix(),
+            vec![],
+            self.backer_threshold,
+            self.backers.clone(),
+        )?;
+
+        let seal = to_event_seal(&vcp)?;
+        let ixn = kerl.make_ixn_with_seal(&vec![seal], &km)?;
+
+        let ixn_source_seal = to_source_seal(&ixn)?;
+
+        tel.incept_tel(vcp, ixn_source_seal)?;
+
+        Ok(Controller {
+            derivation: self.derivation.clone(),
+            flush_every_write: self.durable,
+            ..Controller::from_parts(km, Arc::new(kerl), Arc::new(tel), db_dir_path)
+        })
+    }
+}
+
+#[test]
+pub fn test_builder() -> Result<(), Error> {
+    use keri::{
+        derivation::basic::Basic,
+        prefix::Prefix,
+        signer::{CryptoBox, KeyManager},
+    };
+
+    use super::{MessageHash, UpdateType};
+
+    let backer = IdentifierPrefix::Basic(Basic::Ed25519.derive(CryptoBox::new()?.public_key()));
+    let unused_dir = tempfile::tempdir()?;
+    let controller = ControllerBuilder::new()
+        .serialization(SerializationFormats::JSON)
+        .derivation(SelfAddressing::SHA3_256)
+        .backers(vec![backer.clone()])
+        .backer_threshold(1)
+        .temporary(true)
+        .build(CryptoBox::new()?, unused_dir.path())?;
+    assert!(unused_dir.path().read_dir()?.next().is_none());
+    assert!(!controller.flush_every_write);
+
+    let management_events = controller.tel.get_management_events()?.unwrap();
+    assert!(String::from_utf8(management_events)
+        .unwrap()
+        .contains(&backer.to_str()));
+
+    let message = "some vc";
+    controller.update(UpdateType::Issue(message.into()))?;
+    let expected = MessageHash::new_with(message.as_bytes(), SelfAddressing::SHA3_256);
+    assert_eq!(
+        controller.tel.list_issued()?[0].to_str(),
+        expected.to_string()
+    );
+
+    // Threshold can't exceed number of backers.
+    let dir = tempfile::tempdir()?;
+    assert!(ControllerBuilder::new()
+        .backer_threshold(1)
+        .build(CryptoBox::new()?, dir.path())
+        .is_err());
+
+    let dir = tempfile::tempdir()?;
+    let dispatcher = ControllerBuilder::new()
+        .queue_capacity(1)
+        .durable(true)
+        .build_dispatcher(CryptoBox::new()?, dir.path())?;
+    assert!(dir.path().join(LAYOUT_MARKER).exists());
+    assert!(dispatcher.controller.read().unwrap().flush_every_write);
+    assert!(dispatcher
+        .controller
+        .read()
+        .unwrap()
+        .tel
+        .list_issued()?
+        .is_empty());
+
+    Ok(())
+}
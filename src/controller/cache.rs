@@ -0,0 +1,97 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use keri::{
+    prefix::{Prefix, SelfAddressingPrefix},
+    state::IdentifierState,
+};
+
+// Default number of cached signing states.
+pub const DEFAULT_CACHE_SIZE: usize = 128;
+
+// Least recently used cache of issuer states which signed vcs. Entries are
+// keyed by vc hash and sn of last event of issuer's KEL, so any new KEL
+// event, e.g. rotation, makes older entries unreachable. Vc state is never
+// cached, so revocation is always checked. Cache keeps at most `capacity`
+// entries, zero disables it.
+#[derive(Debug)]
+pub struct VerificationCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    // State with tick of its last use.
+    states: HashMap<(String, u64), (IdentifierState, u64)>,
+    tick: u64,
+}
+
+impl VerificationCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    pub fn get(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+        issuer_sn: u64,
+    ) -> Option<IdentifierState> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.tick += 1;
+        let tick = entries.tick;
+        entries
+            .states
+            .get_mut(&(message_hash.to_str(), issuer_sn))
+            .map(|(state, last_used)| {
+                *last_used = tick;
+                state.clone()
+            })
+    }
+
+    pub fn insert(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+        issuer_sn: u64,
+        state: IdentifierState,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.tick += 1;
+        let tick = entries.tick;
+        let key = (message_hash.to_str(), issuer_sn);
+        if entries.states.len() >= self.capacity && !entries.states.contains_key(&key) {
+            let least_recent = entries
+                .states
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(least_recent) = least_recent {
+                entries.states.remove(&least_recent);
+            }
+        }
+        entries.states.insert(key, (state, tick));
+    }
+
+    // Removes all entries of vc, e.g. after its revocation.
+    pub fn invalidate(&self, message_hash: &SelfAddressingPrefix) {
+        let hash = message_hash.to_str();
+        self.entries
+            .lock()
+            .unwrap()
+            .states
+            .retain(|(entry_hash, _), _| entry_hash != &hash);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use keri::{
+    prefix::{AttachedSignaturePrefix, IdentifierPrefix, Prefix},
+    signer::KeyManager,
+};
+use rand::{rngs::OsRng, RngCore};
+
+use super::Controller;
+use crate::{error::Error, kerl::verify_signatures};
+
+// Maximal number of verified responses remembered for replay detection.
+pub const MAX_SEEN_CHALLENGES: usize = 1024;
+
+#[derive(Clone, Debug)]
+pub struct ChallengeResponse {
+    pub prefix: IdentifierPrefix,
+    // Sequence number of the last event of signer's KEL at the moment of
+    // signing.
+    pub sn: u64,
+    pub nonce: Vec<u8>,
+    // Number of responses made by signer before this one, counted from
+    // random start. It isn't stored, so controller starts from other random
+    // value when loaded, and counters of its responses are unlikely to repeat.
+    pub counter: u64,
+    pub signatures: Vec<AttachedSignaturePrefix>,
+}
+
+impl ChallengeResponse {
+    // Signed payload: nonce followed by big endian counter.
+    fn payload(&self) -> Vec<u8> {
+        signed_payload(&self.nonce, self.counter)
+    }
+}
+
+// Counter of own responses and responses of others already verified, which
+// can't be accepted again.
+#[derive(Debug)]
+pub(crate) struct Challenges {
+    counter: AtomicU64,
+    // Signer prefix, nonce and counter of verified responses, the latest
+    // last.
+    seen: Mutex<VecDeque<(String, Vec<u8>, u64)>>,
+}
+
+impl Default for Challenges {
+    // Counter starts from random value in lower half of range, so it
+    // doesn't wrap around.
+    fn default() -> Self {
+        Self {
+            counter: AtomicU64::new(OsRng.next_u64() >> 1),
+            seen: Mutex::new(VecDeque::new()),
+        }
+    }
+}
+
+impl<K: KeyManager + Send + Sync> Controller<K> {
+    pub fn respond_to_challenge(&self, nonce: &[u8]) -> Result<ChallengeResponse, Error> {
+        let state = self
+            .kerl
+            .get_state()?
+            .ok_or(Error::Generic("Identifier is not incepted".into()))?;
+        let counter = self.challenges.counter.fetch_add(1, Ordering::SeqCst);
+        let signature = self.sign(&signed_payload(nonce, counter))?;
+        Ok(ChallengeResponse {
+            prefix: state.prefix,
+            sn: state.sn,
+            nonce: nonce.to_vec(),
+            counter,
+            signatures: vec![signature.to_attached()],
+        })
+    }
+
+    // Checks response signatures against keys of signer's KEL state at
+    // response sn. Keys must still be current, so signatures made with keys
+    // which were rotated out are rejected. Response with the same nonce and
+    // counter as one of `window` last verified responses is rejected as
+    // replayed. Window can't exceed `MAX_SEEN_CHALLENGES`.
+    pub fn verify_challenge_response(
+        &self,
+        nonce: &[u8],
+        response: &ChallengeResponse,
+        window: usize,
+    ) -> Result<bool, Error> {
+        if response.nonce != nonce {
+            return Ok(false);
+        }
+        let state = self
+            .kerl
+            .get_state_at_sn(&response.prefix, response.sn)?
+            .ok_or(Error::Generic("Unknown signer state".into()))?;
+        let current_state = self
+            .kerl
+            .get_state_for_prefix(&response.prefix)?
+            .ok_or(Error::Generic("Unknown signer state".into()))?;
+        if state.current.public_keys != current_state.current.public_keys {
+            return Ok(false);
+        }
+        if !verify_signatures(&state, &response.payload(), &response.signatures)? {
+            return Ok(false);
+        }
+
+        // Lock is held only for replay check and insertion, so they are
+        // atomic.
+        let seen_entry = (
+            response.prefix.to_str(),
+            response.nonce.clone(),
+            response.counter,
+        );
+        let mut seen = self.challenges.seen.lock().unwrap();
+        if seen
+            .iter()
+            .rev()
+            .take(window)
+            .any(|entry| entry == &seen_entry)
+        {
+            return Ok(false);
+        }
+        seen.push_back(seen_entry);
+        if seen.len() > MAX_SEEN_CHALLENGES {
+            seen.pop_front();
+        }
+        Ok(true)
+    }
+}
+
+fn signed_payload(nonce: &[u8], counter: u64) -> Vec<u8> {
+    [nonce, &counter.to_be_bytes()].concat()
+}
+
+#[test]
+pub fn test_challenge_response() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+
+    let mut nonce = [0u8; 32];
+    OsRng.fill_bytes(&mut nonce);
+    let response = controller.respond_to_challenge(&nonce)?;
+    assert_eq!(response.prefix, controller.kerl.get_prefix());
+    assert_eq!(response.signatures[0].index, 0);
+    assert!(controller.verify_challenge_response(&nonce, &response, 10)?);
+
+    let mut other_nonce = [0u8; 32];
+    OsRng.fill_bytes(&mut other_nonce);
+    assert!(!controller.verify_challenge_response(&other_nonce, &response, 10)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_challenge_replay() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    let mut nonce = [0u8; 32];
+    OsRng.fill_bytes(&mut nonce);
+    let response = controller.respond_to_challenge(&nonce)?;
+    assert!(controller.verify_challenge_response(&nonce, &response, 10)?);
+    // Replayed response.
+    assert!(!controller.verify_challenge_response(&nonce, &response, 10)?);
+
+    // Counter is signed, so it can't be changed to pass replay check.
+    let tampered = ChallengeResponse {
+        counter: response.counter + 1,
+        ..response.clone()
+    };
+    assert!(!controller.verify_challenge_response(&nonce, &tampered, 10)?);
+
+    // Response to the same nonce gets next counter.
+    let next = controller.respond_to_challenge(&nonce)?;
+    assert_eq!(next.counter, response.counter + 1);
+    assert!(controller.verify_challenge_response(&nonce, &next, 10)?);
+
+    // Response outside of window isn't remembered.
+    for _ in 0..2 {
+        OsRng.fill_bytes(&mut nonce);
+        let response = controller.respond_to_challenge(&nonce)?;
+        assert!(controller.verify_challenge_response(&nonce, &response, 10)?);
+    }
+    assert!(controller.verify_challenge_response(&next.nonce, &next, 2)?);
+    assert!(!controller.verify_challenge_response(&next.nonce, &next, 2)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_challenge_counter_start() {
+    // Counters of controllers loaded at different times don't start from the
+    // same value.
+    let (first, second) = (Challenges::default(), Challenges::default());
+    assert_ne!(
+        first.counter.load(Ordering::SeqCst),
+        second.counter.load(Ordering::SeqCst)
+    );
+}
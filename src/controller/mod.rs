@@ -1,45 +1,111 @@
 use std::{
+    collections::HashMap,
     fmt::{Debug, Display},
-    path::Path,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
     str::FromStr,
-    sync::{Arc, RwLock},
+    sync::{Arc, Mutex, RwLock},
+    time::SystemTime,
 };
 
 use crate::{
     error::Error,
+    key_manager::seeded::{EncryptedSeed, ExportableKeyManager},
+    metrics::{Metrics, MetricsSnapshot, Operation},
     task::{
-        controller_tasks::{IssueTask, RevokeTask},
+        controller_tasks::{IssueTask, RevokeTask, UpdateBackersTask},
         kel_tasks::GetKelTask,
         key_manager_tasks::SignMessageTask,
         tel_tasks::GetTelTask,
-        HandleResult,
+        TaskResponse,
     },
-    task_manager::TaskManager,
+    task_manager::{TaskManager, DEFAULT_POLL_INTERVAL},
 };
-use crate::{kerl::KERL, tel::Tel};
-use crossbeam_channel::Sender;
+use crate::{
+    kerl::{enough_signatures, find_seal, matched_indexes, signing_key, KERL},
+    tel::{
+        canonical_bytes, format_datetime, is_revocation, validate_datetime, RevocationReason, Tel,
+        VcStatusKind,
+    },
+};
+use anchoring::{AnchorProof, AnchorQueue, AnchoringFailure, TimedProof};
+use builder::{ControllerBuilder, DEFAULT_QUEUE_CAPACITY};
+use cache::{VerificationCache, DEFAULT_CACHE_SIZE};
+use challenge::Challenges;
+use crossbeam_channel::{Receiver, Sender};
 use keri::{
     derivation::self_addressing::SelfAddressing,
     event::{
+        event_data::EventData,
         sections::seal::{EventSeal, Seal},
         EventMessage,
     },
-    prefix::{Prefix, SelfAddressingPrefix},
+    event_message::SignedEventMessage,
+    prefix::{
+        AttachedSignaturePrefix, BasicPrefix, IdentifierPrefix, Prefix, SelfAddressingPrefix,
+    },
     signer::KeyManager,
+    state::IdentifierState,
 };
-use teliox::{event::Event, seal::EventSourceSeal};
+use log::{error, warn};
+use notification::{RegistryEvent, Subscribers};
+use signature::Signature;
+use snapshot::Snapshot;
+use teliox::{
+    event::Event,
+    seal::EventSourceSeal,
+    state::{vc_state::TelState, State},
+};
+use tempfile::TempDir;
+use verifier::CredentialVerifier;
+
+pub mod anchoring;
+pub mod builder;
+pub mod cache;
+pub mod challenge;
+pub mod notification;
+pub mod oobi;
+pub mod signature;
+pub mod snapshot;
+pub mod status_proof;
+pub mod verifier;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct MessageHash {
     sai: SelfAddressingPrefix,
 }
 
 impl MessageHash {
     pub fn new(data: &[u8]) -> Self {
+        Self::new_with(data, SelfAddressing::Blake3_256)
+    }
+
+    pub fn new_with(data: &[u8], derivation: SelfAddressing) -> Self {
         Self {
-            sai: SelfAddressing::Blake3_256.derive(data),
+            sai: derivation.derive(data),
         }
     }
+
+    // Returns binary form of qualified hash, with derivation code bits
+    // followed by digest, as in CESR binary domain.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Qualified base64 length is a multiple of 4, so it decodes without
+        // padding.
+        base64::decode_config(self.sai.to_str(), base64::URL_SAFE)
+            .expect("Qualified hash is valid base64")
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        base64::encode_config(bytes, base64::URL_SAFE).parse()
+    }
+}
+
+impl Eq for MessageHash {}
+
+impl Hash for MessageHash {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.sai.to_str().hash(state)
+    }
 }
 
 impl Display for MessageHash {
@@ -65,95 +131,1255 @@ impl FromStr for MessageHash {
     }
 }
 
+// Result of message verification with details of keys which were used.
+#[derive(Debug)]
+pub struct VerificationOutcome {
+    pub verified: bool,
+    // Keys of issuer which verified the signature.
+    pub matched_keys: Vec<BasicPrefix>,
+    // Sn of establishment event which set keys current at issuance. Missing
+    // if message wasn't issued.
+    pub establishment_sn: Option<u64>,
+    pub credential_state: TelState,
+}
+
+// Events which issuance of message would generate, not processed yet.
+// Anchoring event isn't signed.
+#[derive(Debug)]
+pub struct IssuancePreview {
+    pub tel_event: Event,
+    // Seal of tel event, anchored in KEL event.
+    pub event_seal: Seal,
+    pub anchoring_event: EventMessage,
+    // Seal of KEL event, attached to tel event.
+    pub source_seal: EventSourceSeal,
+}
+
+// Result of batch revocation.
+#[derive(Debug, Default)]
+pub struct BatchRevocation {
+    pub revoked: Vec<SelfAddressingPrefix>,
+    // Vcs which couldn't be revoked, with their state.
+    pub skipped: Vec<(SelfAddressingPrefix, VcStatusKind)>,
+}
+
 pub enum UpdateType {
     Issue(String),
+    IssueBytes(Vec<u8>),
     Revoke(MessageHash),
 }
 
+// Mutating operations return before sled databases of KEL and TEL are
+// flushed, unless `with_flush_every_write` or `durable` of builder is set.
+// Sled flushes them periodically and when controller is dropped.
 #[derive(Debug)]
 pub struct Controller<K: KeyManager + Send + Sync + 'static> {
     key_manager: Arc<K>,
     kerl: Arc<KERL>,
     tel: Arc<Tel>,
+    anchor_queue: AnchorQueue,
+    // Held shared by updates from anchoring until their tel events are
+    // processed, and exclusively while taking snapshot.
+    commit: RwLock<()>,
+    subscribers: Subscribers,
+    // Tel events anchored in KEL, which failed to be processed. They are
+    // kept only in memory, until `repair`.
+    orphaned: Mutex<Vec<(Event, EventSourceSeal)>>,
+    // Derivation of hashes of issued messages. It isn't stored in database,
+    // so it needs to be set again after load.
+    derivation: SelfAddressing,
+    verification_cache: VerificationCache,
+    // Named registries, besides the default one in `tel`. Their inceptions
+    // are anchored in the same KEL.
+    registries: RwLock<HashMap<String, Arc<Tel>>>,
+    db_dir: PathBuf,
+    // Directory of databases of temporary controller, removed when
+    // controller is dropped.
+    temp_dir: Option<TempDir>,
+    // Shared with task manager, when controller is run by dispatcher.
+    metrics: Arc<Metrics>,
+    challenges: Challenges,
+    // Flush databases after every mutating operation. It isn't stored in
+    // database, so it needs to be set again after load.
+    flush_every_write: bool,
 }
 
 impl<K: KeyManager + Send + Sync> Controller<K> {
+    // Incepts controller with default configuration, see `ControllerBuilder`.
     pub fn init(km: K, db_dir_path: &Path) -> Result<Self, Error> {
-        let tel_db_path = db_dir_path.join(Path::new("./kel"));
-        let kel_db_path = db_dir_path.join(Path::new("./tel"));
-        let mut tel = Tel::new(tel_db_path.as_path())?;
-        let mut kerl = KERL::new(kel_db_path.as_path())?;
-        kerl.incept(&km)?;
+        ControllerBuilder::default().build(km, db_dir_path)
+    }
+
+    // Rebuilds KEL and TEL databases in `db_dir_path` from exported streams.
+    // Tel stream should start with management events. Every tel event needs
+    // to be anchored in restored KEL, and key manager needs to hold its
+    // current keys, so issuing can be continued.
+    pub fn restore_from_streams(
+        km: K,
+        db_dir_path: &Path,
+        kel: &[u8],
+        tel: &[u8],
+    ) -> Result<Self, Error> {
+        let (kel_db_path, tel_db_path) = resolve_db_paths(db_dir_path);
+        if KERL::is_incepted(&kel_db_path) || is_legacy_layout(db_dir_path) {
+            return Err(Error::Generic(
+                "Database directory already contains KEL, use load instead".into(),
+            ));
+        }
+        std::fs::create_dir_all(db_dir_path)?;
+        std::fs::File::create(db_dir_path.join(LAYOUT_MARKER))?;
+        let kerl = KERL::restore(kel_db_path.as_path(), kel)?;
+        let current_key = signing_key(&km)?.0;
+        let holds_current_keys = kerl
+            .get_state()?
+            .map(|state| state.current.public_keys.contains(&current_key))
+            .unwrap_or(false);
+        if !holds_current_keys {
+            return Err(Error::Generic(
+                "Key manager doesn't hold current keys of restored KEL".into(),
+            ));
+        }
+
+        let mut restored_tel = Tel::new(tel_db_path.as_path())?;
+        restored_tel.ingest(tel, &kerl)?;
+        if restored_tel.get_issuer()? != kerl.get_prefix() {
+            return Err(Error::Generic(
+                "Registry wasn't incepted by restored identifier".into(),
+            ));
+        }
+
+        Ok(Controller::from_parts(
+            km,
+            Arc::new(kerl),
+            Arc::new(restored_tel),
+            db_dir_path,
+        ))
+    }
 
+    // Attaches new registry to already incepted KEL, managed elsewhere until
+    // now. Registry inception is anchored in the next event of the KEL. Key
+    // manager needs to hold current keys of the KEL. Controller made this way
+    // can't be opened with `load`, which expects registry inception anchored
+    // right after KEL inception.
+    pub fn with_kerl(kerl: KERL, tel_db_path: &Path, km: K) -> Result<Self, Error> {
+        let state = match kerl.get_state()? {
+            Some(state) if kerl.get_prefix() != IdentifierPrefix::default() => state,
+            _ => return Err(Error::Generic("KEL is not incepted".into())),
+        };
+        if !state.current.public_keys.contains(&signing_key(&km)?.0) {
+            return Err(Error::Generic(
+                "Key manager doesn't hold current keys of KEL".into(),
+            ));
+        }
+        let mut tel = Tel::new(tel_db_path)?;
         let vcp = tel.make_inception_event(kerl.get_prefix(), vec![], 0, vec![])?;
+        let ixn = kerl.make_ixn_with_seal(&[to_event_seal(&vcp)?], &km)?;
+        tel.incept_tel(vcp, to_source_seal(&ixn)?)?;
 
-        let seal = to_event_seal(&vcp)?;
-        let ixn = kerl.make_ixn_with_seal(&vec![seal], &km)?;
+        // Named registries are kept next to tel database.
+        let db_dir = tel_db_path.parent().unwrap_or(tel_db_path);
+        Ok(Controller::from_parts(
+            km,
+            Arc::new(kerl),
+            Arc::new(tel),
+            db_dir,
+        ))
+    }
 
-        let ixn_source_seal = to_source_seal(&ixn.event_message)?;
+    // Opens KEL and TEL databases created by `init` without incepting again.
+    pub fn load(km: K, db_dir_path: &Path) -> Result<Self, Error> {
+        let (kel_db_path, tel_db_path) = resolve_db_paths(db_dir_path);
+        let kerl = KERL::from_db(kel_db_path.as_path())?;
 
-        tel.incept_tel(vcp, ixn_source_seal)?;
+        // Tel inception event is anchored in the first interaction event.
+        let tel_prefix = match kerl
+            .get_event_at_sn(&kerl.get_prefix(), 1)?
+            .map(|ev| ev.event.event_data)
+        {
+            Some(EventData::Ixn(ixn)) => ixn.data.into_iter().find_map(|seal| match seal {
+                Seal::Event(es) => Some(es.prefix),
+                _ => None,
+            }),
+            _ => None,
+        }
+        .ok_or(Error::Generic("Missing tel inception seal".into()))?;
+        let tel = Tel::load(tel_db_path.as_path(), tel_prefix)?;
 
         Ok(Controller {
-            key_manager: Arc::new(km),
-            kerl: Arc::new(kerl),
-            tel: Arc::new(tel),
-            // TODO remove magic number
+            registries: RwLock::new(load_registries(db_dir_path)?),
+            ..Controller::from_parts(km, Arc::new(kerl), Arc::new(tel), db_dir_path)
         })
     }
 
+    // Makes controller of already opened databases, with default settings
+    // and empty runtime state.
+    pub(crate) fn from_parts(km: K, kerl: Arc<KERL>, tel: Arc<Tel>, db_dir: &Path) -> Self {
+        Controller {
+            key_manager: Arc::new(km),
+            kerl,
+            tel,
+            anchor_queue: AnchorQueue::new(),
+            commit: RwLock::new(()),
+            subscribers: Subscribers::new(),
+            orphaned: Mutex::new(vec![]),
+            derivation: SelfAddressing::Blake3_256,
+            verification_cache: VerificationCache::new(DEFAULT_CACHE_SIZE),
+            registries: RwLock::new(HashMap::new()),
+            db_dir: db_dir.to_path_buf(),
+            temp_dir: None,
+            metrics: Arc::new(Metrics::default()),
+            challenges: Challenges::default(),
+            flush_every_write: false,
+        }
+    }
+
+    // Loads controller if KEL in `db_dir_path` is already incepted, and
+    // incepts new one otherwise. Returns controller along with flag set if
+    // it was newly created.
+    pub fn open_or_init(km: K, db_dir_path: &Path) -> Result<(Self, bool), Error> {
+        let (kel_db_path, _) = resolve_db_paths(db_dir_path);
+        if KERL::is_incepted(&kel_db_path) {
+            Ok((Controller::load(km, db_dir_path)?, false))
+        } else {
+            Ok((Controller::init(km, db_dir_path)?, true))
+        }
+    }
+
+    // Update can be called concurrently. Tel events of concurrent updates are
+    // anchored in KEL in batches, see `AnchorQueue`.
+    // Current time is recorded as local datetime of the update.
     pub fn update(&self, up_type: UpdateType) -> Result<(), Error> {
+        self.update_tel(&self.tel, up_type, None, None)
+    }
+
+    // Updates tel and records given ISO-8601 datetime of the update as local,
+    // unverified metadata, see `Tel::set_event_datetime`.
+    pub fn update_at(&self, up_type: UpdateType, datetime: Option<&str>) -> Result<(), Error> {
+        self.update_tel(&self.tel, up_type, datetime, None)
+    }
+
+    // Revokes vc and records reason of revocation as local, unverified
+    // metadata, see `Tel::set_revocation_reason`. `update` with
+    // `UpdateType::Revoke` revokes with unspecified reason.
+    pub fn revoke(&self, hash: MessageHash, reason: RevocationReason) -> Result<(), Error> {
+        self.update_tel(&self.tel, UpdateType::Revoke(hash), None, Some(reason))
+    }
+
+    // Updates named registry made with `create_registry`.
+    pub fn update_in(&self, registry: &str, up_type: UpdateType) -> Result<(), Error> {
+        self.update_tel(&self.registry(registry)?, up_type, None, None)
+    }
+
+    fn update_tel(
+        &self,
+        tel: &Tel,
+        up_type: UpdateType,
+        datetime: Option<&str>,
+        reason: Option<RevocationReason>,
+    ) -> Result<(), Error> {
+        if self.kerl.is_abandoned()? {
+            return Err(Error::Abandoned);
+        }
+        // Datetime is checked before anchoring, so invalid one doesn't leave
+        // event without it.
+        let datetime = match datetime {
+            Some(datetime) => {
+                validate_datetime(datetime)?;
+                datetime.to_string()
+            }
+            None => format_datetime(SystemTime::now()),
+        };
+        let operation = match up_type {
+            UpdateType::Revoke(_) => Operation::Revoked,
+            _ => Operation::Issued,
+        };
         let ev = match up_type {
-            UpdateType::Issue(message) => self.tel.make_issuance_event(&message),
-            UpdateType::Revoke(hash) => self.tel.make_revoke_event(&hash.to_string()),
-        }?;
+            UpdateType::Issue(message) => {
+                tel.make_issuance_event_for(self.message_hash(message.as_bytes()))
+            }
+            UpdateType::IssueBytes(message) => {
+                tel.make_issuance_event_for(self.message_hash(&message))
+            }
+            UpdateType::Revoke(hash) => tel.make_revoke_event(&hash.to_string()),
+        };
+        let result = ev.and_then(|ev| {
+            let (prefix, sn) = (ev.get_prefix(), ev.get_sn());
+            // Reason is recorded before revocation is processed. It's read
+            // only for revocation at this sn, so it shows up together with
+            // the revocation, and reason of failed one is replaced by the
+            // next revocation.
+            if is_revocation(&ev) {
+                if let IdentifierPrefix::SelfAddressing(hash) = &prefix {
+                    tel.set_revocation_reason(hash, sn, reason.unwrap_or_default())?;
+                }
+            }
+            self.anchor_and_process(ev, |ev, seal| tel.process(ev, seal))?;
+            if let IdentifierPrefix::SelfAddressing(hash) = prefix {
+                tel.set_event_datetime(&hash, sn, &datetime)?;
+            }
+            self.flush_written()
+        });
+        match &result {
+            Ok(_) => self.metrics.record(operation),
+            Err(e) => {
+                error!("Update of tel failed: {}", e);
+                self.metrics.record(Operation::Failed)
+            }
+        }
+        result
+    }
 
-        let seal = to_event_seal(&ev)?;
-        let ixn = self.kerl.make_ixn_seal(&vec![seal])?;
-        let serialized_ixn = ixn.serialize().unwrap();
-        let signature = self.key_manager.sign(&ixn.serialize().unwrap()).unwrap();
-        self.kerl.process(&serialized_ixn, &signature)?;
+    // Revokes vcs with revocation events anchored in a single KEL event. Vcs
+    // which aren't issued are skipped and reported along with their state.
+    pub fn revoke_batch(&self, hashes: &[SelfAddressingPrefix]) -> Result<BatchRevocation, Error> {
+        if self.kerl.is_abandoned()? {
+            return Err(Error::Abandoned);
+        }
+        let mut report = BatchRevocation::default();
+        let mut events = vec![];
+        for hash in hashes {
+            // Repeated hash is revoked already by this batch.
+            if report.revoked.contains(hash) {
+                report.skipped.push((hash.clone(), VcStatusKind::Revoked));
+                continue;
+            }
+            match self.tel.make_revoke_event(&hash.to_str()) {
+                Ok(event) => {
+                    events.push(event);
+                    report.revoked.push(hash.clone());
+                }
+                Err(Error::CannotRevoke { state }) => report.skipped.push((hash.clone(), state)),
+                Err(e) => return Err(e),
+            }
+        }
+        if events.is_empty() {
+            return Ok(report);
+        }
 
+        let _commit = self.commit.read().unwrap();
+        let ixn = self
+            .anchor_queue
+            .anchor_all(events.clone(), |events| self.anchor_tel_events(events))?;
         let ixn_source_seal = to_source_seal(&ixn)?;
+        let mut orphaned = vec![];
+        for event in events {
+            match self.tel.process(event.clone(), ixn_source_seal.clone()) {
+                Ok(_) => self.notify(&event),
+                Err(_) => orphaned.push((event, ixn_source_seal.clone())),
+            }
+        }
+        if !orphaned.is_empty() {
+            warn!(
+                "{} tel events anchored at KEL sn {} failed to be processed, kept for repair",
+                orphaned.len(),
+                ixn_source_seal.sn
+            );
+            self.orphaned.lock().unwrap().extend(orphaned);
+            self.metrics.record(Operation::Failed);
+            return Err(Error::Inconsistent {
+                kel_sn: ixn_source_seal.sn,
+            });
+        }
+        self.metrics
+            .record_many(Operation::Revoked, report.revoked.len() as u64);
+        self.flush_written()?;
+        Ok(report)
+    }
 
-        self.tel.process(ev, ixn_source_seal)?;
-        Ok(())
+    // Creates new registry of controller's identifier, with inception
+    // anchored in controller's KEL. Registries are independent, so the same
+    // message can be issued in many of them. Verification methods of
+    // controller use the default registry.
+    pub fn create_registry(&self, name: &str) -> Result<Arc<Tel>, Error> {
+        let valid_name = name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if name.is_empty() || !valid_name {
+            return Err(Error::Generic(format!("Invalid registry name: {}", name)));
+        }
+        if self.kerl.is_abandoned()? {
+            return Err(Error::Abandoned);
+        }
+        let mut registries = self.registries.write().unwrap();
+        if registries.contains_key(name) {
+            return Err(Error::Generic(format!("Registry {} already exists", name)));
+        }
+
+        let path = self.db_dir.join(REGISTRIES_DIR).join(name);
+        let mut tel = Tel::new(&path)?;
+        let vcp = tel.make_inception_event(self.kerl.get_prefix(), vec![], 0, vec![])?;
+        let ixn = {
+            let _commit = self.commit.read().unwrap();
+            self.anchor_queue
+                .anchor(vcp.clone(), |events| self.anchor_tel_events(events))?
+        };
+        tel.incept_tel(vcp, to_source_seal(&ixn)?)?;
+        std::fs::write(
+            path.join(REGISTRY_PREFIX),
+            tel.get_management_tel_state()?.prefix.to_str(),
+        )?;
+
+        if self.flush_every_write {
+            tel.flush()?;
+        }
+        let tel = Arc::new(tel);
+        registries.insert(name.to_string(), Arc::clone(&tel));
+        Ok(tel)
+    }
+
+    // Returns named registry made with `create_registry`.
+    pub fn registry(&self, name: &str) -> Result<Arc<Tel>, Error> {
+        self.registries
+            .read()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or(Error::Generic(format!("Unknown registry: {}", name)))
+    }
+
+    pub fn update_backers(
+        &self,
+        ba: &[IdentifierPrefix],
+        br: &[IdentifierPrefix],
+    ) -> Result<(), Error> {
+        if self.kerl.is_abandoned()? {
+            return Err(Error::Abandoned);
+        }
+        let rot = self.tel.make_rotation_event(ba, br)?;
+        self.anchor_and_process(rot, |ev, seal| self.tel.process(ev, seal))
+    }
+
+    // Anchors tel event in KEL and processes it with `process`. KEL and TEL
+    // can't be written atomically, so if processing fails after anchoring,
+    // event is kept for `repair` and `Error::Inconsistent` is returned.
+    fn anchor_and_process<F>(&self, event: Event, process: F) -> Result<(), Error>
+    where
+        F: Fn(Event, EventSourceSeal) -> Result<State, Error>,
+    {
+        self.anchor_with_and_process(event, |events| self.anchor_tel_events(events), process)
+    }
+
+    // Same as `anchor_and_process`, but anchoring event of queued tel events
+    // is made and processed by `anchor`.
+    fn anchor_with_and_process<A, F>(
+        &self,
+        event: Event,
+        anchor: A,
+        process: F,
+    ) -> Result<(), Error>
+    where
+        A: Fn(&[Event]) -> Result<SignedEventMessage, Error>,
+        F: Fn(Event, EventSourceSeal) -> Result<State, Error>,
+    {
+        let _commit = self.commit.read().unwrap();
+        let ixn = self.anchor_queue.anchor(event.clone(), anchor)?;
+        let ixn_source_seal = to_source_seal(&ixn)?;
+
+        if let Err(e) = process(event.clone(), ixn_source_seal.clone()) {
+            let kel_sn = ixn_source_seal.sn;
+            warn!(
+                "Tel event anchored at KEL sn {} failed to be processed, kept for repair: {}",
+                kel_sn, e
+            );
+            self.orphaned.lock().unwrap().push((event, ixn_source_seal));
+            return Err(Error::Inconsistent { kel_sn });
+        }
+        self.notify(&event);
+        self.flush_written()
+    }
+
+    // Processes tel events which were anchored in KEL, but failed to be
+    // processed. Returns number of repaired events. Events which fail again
+    // are kept for next repair.
+    pub fn repair(&self) -> Result<usize, Error> {
+        let _commit = self.commit.read().unwrap();
+        let mut orphaned = self.orphaned.lock().unwrap();
+        let mut repaired = 0;
+        for (event, seal) in std::mem::take(&mut *orphaned) {
+            match self.tel.process(event.clone(), seal.clone()) {
+                Ok(_) => {
+                    self.notify(&event);
+                    repaired += 1;
+                }
+                Err(e) => {
+                    warn!(
+                        "Repair of tel event anchored at KEL sn {} failed: {}",
+                        seal.sn, e
+                    );
+                    orphaned.push((event, seal))
+                }
+            }
+        }
+        self.flush_written()?;
+        Ok(repaired)
+    }
+
+    // Issues message of identifier with many current keys. Anchoring event
+    // and message are signed by all signers, with indexes of their keys in
+    // current key list. Returns signatures of message.
+    pub fn issue_multisig(
+        &self,
+        message: &[u8],
+        signers: &[&K],
+    ) -> Result<Vec<AttachedSignaturePrefix>, Error> {
+        if self.kerl.is_abandoned()? {
+            return Err(Error::Abandoned);
+        }
+        let result = self
+            .tel
+            .make_issuance_event_for(self.message_hash(message))
+            .and_then(|ev| {
+                self.anchor_with_and_process(
+                    ev,
+                    |events| {
+                        let seals = events
+                            .iter()
+                            .map(to_event_seal)
+                            .collect::<Result<Vec<_>, _>>()?;
+                        let ixn = self.kerl.make_ixn_seal(&seals)?;
+                        let signatures = self.sign_by_current_keys(&ixn.serialize()?, signers)?;
+                        self.kerl.process_with_signatures(&ixn, signatures)
+                    },
+                    |ev, seal| self.tel.process(ev, seal),
+                )
+            });
+        match &result {
+            Ok(_) => self.metrics.record(Operation::Issued),
+            Err(e) => {
+                error!("Multisig issuance failed: {}", e);
+                self.metrics.record(Operation::Failed)
+            }
+        }
+        result?;
+
+        self.sign_by_current_keys(message, signers)
+    }
+
+    // Signs message with every signer, using index of signer's key in current
+    // key list.
+    fn sign_by_current_keys(
+        &self,
+        message: &[u8],
+        signers: &[&K],
+    ) -> Result<Vec<AttachedSignaturePrefix>, Error> {
+        let current_keys = self
+            .kerl
+            .get_state()?
+            .ok_or(Error::Generic("Identifier is not incepted".into()))?
+            .current
+            .public_keys;
+        signers
+            .iter()
+            .map(|signer| {
+                let (key, code) = signing_key(*signer)?;
+                let index = current_keys
+                    .iter()
+                    .position(|current| current == &key)
+                    .ok_or(Error::Generic("Signer's key is not current".into()))?;
+                let signature = signer
+                    .sign(&message.to_vec())
+                    .map_err(Error::KeyManagerError)?;
+                Ok(AttachedSignaturePrefix::new(code, signature, index as u16))
+            })
+            .collect()
+    }
+
+    // Anchors issuance events of all issued and not revoked vcs again, under
+    // current keys. Afterwards they are verified with keys from the moment
+    // of re-anchoring. Returns number of re-anchored vcs.
+    pub fn reanchor_live(&self) -> Result<usize, Error> {
+        if self.kerl.is_abandoned()? {
+            return Err(Error::Abandoned);
+        }
+        let live = self
+            .tel
+            .get_vc_states(&self.tel.list_issued()?)?
+            .into_iter()
+            .filter(|(_, state)| matches!(state, TelState::Issued(_)))
+            .map(|(hash, _)| hash)
+            .collect::<Vec<_>>();
+
+        let _commit = self.commit.read().unwrap();
+        for hash in &live {
+            let issuance = self
+                .tel
+                .get_tel(hash)?
+                .into_iter()
+                .next()
+                .ok_or(AnchoringFailure::MissingTelEvent)?;
+            let ixn = self
+                .anchor_queue
+                .anchor(issuance.event, |events| self.anchor_tel_events(events))?;
+            self.tel.set_reanchored(hash, &to_source_seal(&ixn)?)?;
+            self.verification_cache.invalidate(hash);
+        }
+        Ok(live.len())
+    }
+
+    // Builds events of message issuance, without signing or processing
+    // them, so KEL and TEL stay unchanged and key manager isn't used.
+    // Anchoring event is the one which would be made, if no other update is
+    // anchored before.
+    pub fn preview_issue(&self, message: &str) -> Result<IssuancePreview, Error> {
+        if self.kerl.is_abandoned()? {
+            return Err(Error::Abandoned);
+        }
+        let tel_event = self
+            .tel
+            .make_issuance_event_for(self.message_hash(message.as_bytes()))?;
+        let event_seal = to_event_seal(&tel_event)?;
+        let anchoring_event = self.kerl.make_ixn_seal(&[event_seal.clone()])?;
+        let source_seal = message_source_seal(&anchoring_event)?;
+        Ok(IssuancePreview {
+            tel_event,
+            event_seal,
+            anchoring_event,
+            source_seal,
+        })
+    }
+
+    // Returns state of vc with given hash as JSON, e.g.
+    // `{"state":"issued","sn":0,"local_issued_at":"2021-07-01T12:00:00Z"}`.
+    pub fn get_vc_status_json(&self, message_hash: &SelfAddressingPrefix) -> Result<String, Error> {
+        let status = self.tel.get_vc_status(message_hash)?;
+        serde_json::to_string(&status).map_err(|e| Error::Generic(e.to_string()))
+    }
+
+    // Returns receiver of registry events of mutating operations completed
+    // after subscribing.
+    pub fn subscribe(&self) -> Receiver<RegistryEvent> {
+        self.subscribers.subscribe()
+    }
+
+    // Notifies subscribers about processed tel event and drops cached
+    // states of revoked vc.
+    fn notify(&self, processed: &Event) {
+        if let Some(event) = RegistryEvent::from_tel_event(processed) {
+            if let RegistryEvent::Revoked(hash) = &event {
+                self.verification_cache.invalidate(hash);
+            }
+            self.subscribers.notify(event);
+        }
+    }
+
+    // Anchors seals of all given tel events in one interaction event. Tel
+    // events still need to be processed with source seal of returned event.
+    pub fn anchor_tel_events(&self, events: &[Event]) -> Result<SignedEventMessage, Error> {
+        let seals = events
+            .iter()
+            .map(to_event_seal)
+            .collect::<Result<Vec<_>, _>>()?;
+        let ixn = self.kerl.make_ixn_seal(&seals)?;
+        let signature = self.sign(&ixn.serialize()?)?;
+        self.kerl
+            .process_with_signatures(&ixn, vec![signature.to_attached()])
     }
 
     // TODO:
     // rotate()
-    // get_pub_key(message_hash)
-    // verify(message, signature)
 
-    pub fn get_tel(&self, message_hash: MessageHash) -> Result<Vec<u8>, Error> {
+    // Returns KEL event which anchors issuance event of vc with given hash.
+    pub fn get_anchoring_event(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+    ) -> Result<EventMessage, Error> {
+        let issuance = self
+            .tel
+            .get_tel(message_hash)?
+            .into_iter()
+            .next()
+            .ok_or(AnchoringFailure::MissingTelEvent)?;
+        let source_seal = issuance.seal.seal;
         Ok(self
+            .kerl
+            .get_event_at_sn(&self.tel.get_issuer()?, source_seal.sn)?
+            .ok_or(AnchoringFailure::MissingKelEvent(source_seal.sn))?)
+    }
+
+    // Checks the whole chain from issuance event of vc to issuer's keys: tel
+    // event, its source seal, anchoring KEL event and seal of tel event in
+    // it.
+    pub fn verify_anchoring(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+    ) -> Result<AnchorProof, Error> {
+        let issuance = self
             .tel
-            .get_tel(&message_hash.clone().into())
-            .unwrap()
+            .get_tel(message_hash)?
+            .into_iter()
+            .next()
+            .ok_or(AnchoringFailure::MissingTelEvent)?;
+        let source_seal = issuance.seal.seal;
+        let issuer = self.tel.get_issuer()?;
+
+        let kel_event = self
+            .kerl
+            .get_event_at_sn(&issuer, source_seal.sn)?
+            .ok_or(AnchoringFailure::MissingKelEvent(source_seal.sn))?;
+        if !source_seal.digest.verify_binding(&kel_event.serialize()?) {
+            return Err(AnchoringFailure::SourceSealMismatch(source_seal.sn).into());
+        }
+        let seal_index = find_seal(&kel_event, &issuance.event)?
+            .ok_or(AnchoringFailure::MissingEventSeal(source_seal.sn))?;
+        let signing_state = self
+            .kerl
+            .get_state_for_seal(&issuer, source_seal.sn, &source_seal.digest)?
+            .ok_or(AnchoringFailure::MissingKelEvent(source_seal.sn))?;
+
+        Ok(AnchorProof {
+            tel_event_digest: SelfAddressing::Blake3_256.derive(&canonical_bytes(&issuance.event)?),
+            kel_event_sn: source_seal.sn,
+            kel_event_digest: source_seal.digest,
+            seal_index,
+            signing_keys: signing_state.current.public_keys,
+        })
+    }
+
+    // Returns KEL event anchoring issuance of vc with given hash, along with
+    // its receipts. Proof of not yet receipted event has no witnesses.
+    pub fn issuance_proof_with_time(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+    ) -> Result<TimedProof, Error> {
+        let anchoring_event = self.get_anchoring_event(message_hash)?;
+        let issuer = self.tel.get_issuer()?;
+        let sn = anchoring_event.event.sn;
+        Ok(TimedProof {
+            receipts: self.kerl.get_transferable_receipts(&issuer, sn)?,
+            witnesses: self.kerl.get_receipt_validators(&issuer, sn)?,
+            anchoring_event,
+        })
+    }
+
+    // Returns keys of local issuer, which were current at the moment of
+    // issuance of message with given hash.
+    pub fn get_pub_key(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+    ) -> Result<Vec<BasicPrefix>, Error> {
+        self.get_pub_key_for(&self.tel.get_issuer()?, message_hash)
+    }
+
+    // Returns keys of given issuer, which were current at the moment of
+    // issuance of message with given hash. Issuer's KEL needs to be
+    // processed first.
+    pub fn get_pub_key_for(
+        &self,
+        issuer: &IdentifierPrefix,
+        message_hash: &SelfAddressingPrefix,
+    ) -> Result<Vec<BasicPrefix>, Error> {
+        if self.kerl.get_state_for_prefix(issuer)?.is_none() {
+            return Err(Error::Generic(format!(
+                "Missing KEL of issuer {}",
+                issuer.to_str()
+            )));
+        }
+        Ok(self
+            .get_signing_state_for(issuer, message_hash)?
+            .current
+            .public_keys)
+    }
+
+    // Checks if message was issued and signature was made with keys of
+    // issuer which were current at the moment of issuance.
+    pub fn verify(&self, message: &str, signature: &Signature) -> Result<bool, Error> {
+        self.verify_bytes(message.as_bytes(), signature)
+    }
+
+    pub fn verify_bytes(&self, message: &[u8], signature: &Signature) -> Result<bool, Error> {
+        Ok(self.verify_detailed(message, signature)?.verified)
+    }
+
+    // Verifies message like `verify_bytes`, and returns keys and state used
+    // for verification.
+    pub fn verify_detailed(
+        &self,
+        message: &[u8],
+        signature: &Signature,
+    ) -> Result<VerificationOutcome, Error> {
+        let message_hash = self.message_hash(message);
+        self.record_verification(self.verify_outcome(message, &message_hash, signature, None))
+    }
+
+    // Logs failed verification and records its outcome in metrics.
+    fn record_verification(
+        &self,
+        outcome: Result<VerificationOutcome, Error>,
+    ) -> Result<VerificationOutcome, Error> {
+        if let Err(e) = &outcome {
+            error!("Verification failed: {}", e);
+        }
+        self.metrics.record(match &outcome {
+            Ok(outcome) if outcome.verified => Operation::Verified,
+            _ => Operation::Failed,
+        });
+        outcome
+    }
+
+    // Verifies signature of message issued as vc with given hash. Vc state
+    // and issuer state are taken from `snapshot` if it's given, otherwise
+    // from current databases. Vcs of abandoned identifier are still
+    // verified, only new events can't be made.
+    fn verify_outcome(
+        &self,
+        message: &[u8],
+        message_hash: &SelfAddressingPrefix,
+        signature: &Signature,
+        snapshot: Option<&Snapshot>,
+    ) -> Result<VerificationOutcome, Error> {
+        let credential_state = match snapshot {
+            Some(snapshot) => snapshot.get_vc_state(message_hash)?,
+            None => self.tel.get_vc_state(message_hash)?,
+        };
+        if !matches!(credential_state, TelState::Issued(_)) {
+            return Ok(VerificationOutcome {
+                verified: false,
+                matched_keys: vec![],
+                establishment_sn: None,
+                credential_state,
+            });
+        }
+        let state = match snapshot {
+            Some(snapshot) => snapshot.get_signing_state(message_hash)?,
+            None => self.get_signing_state(message_hash)?,
+        };
+        let indexes = matched_indexes(&state, message, &[signature.to_attached()]);
+        let matched_keys = indexes
+            .iter()
+            .map(|i| state.current.public_keys[*i].clone())
+            .collect();
+        let establishment_sn = match snapshot {
+            Some(snapshot) => snapshot.get_establishment_sn(&state.prefix, state.sn)?,
+            None => self.kerl.get_establishment_sn(&state.prefix, state.sn)?,
+        };
+        Ok(VerificationOutcome {
+            verified: enough_signatures(&state, &indexes),
+            matched_keys,
+            establishment_sn: Some(establishment_sn),
+            credential_state,
+        })
+    }
+
+    // Checks if message was issued and not revoked as of KEL event with
+    // given sn, and if signature was made with keys of issuer which were
+    // current at the moment of issuance. Later revocation and re-anchoring
+    // don't matter.
+    pub fn verify_at(&self, message: &str, signature: &Signature, sn: u64) -> Result<bool, Error> {
+        let message_hash = self.message_hash(message.as_bytes());
+        let outcome = Snapshot::at_sn(Arc::clone(&self.kerl), Arc::clone(&self.tel), sn).and_then(
+            |snapshot| {
+                self.verify_outcome(
+                    message.as_bytes(),
+                    &message_hash,
+                    signature,
+                    Some(&snapshot),
+                )
+            },
+        );
+        Ok(self.record_verification(outcome)?.verified)
+    }
+
+    // Checks if vc with given SAID is issued and signature over qualified
+    // SAID string was made with keys of issuer which were current at the
+    // moment of issuance. Holder can present the SAID instead of message.
+    pub fn verify_said_signature(
+        &self,
+        said: &SelfAddressingPrefix,
+        signature: &Signature,
+    ) -> Result<bool, Error> {
+        let outcome = self.verify_outcome(said.to_str().as_bytes(), said, signature, None);
+        Ok(self.record_verification(outcome)?.verified)
+    }
+
+    // Sets derivation of hashes of issued messages. Hashes of messages
+    // issued with other derivation don't change.
+    pub fn with_derivation(mut self, derivation: SelfAddressing) -> Self {
+        self.derivation = derivation;
+        self
+    }
+
+    // Sets maximal number of issuer states cached for verification of vcs,
+    // `DEFAULT_CACHE_SIZE` by default. Zero disables the cache.
+    pub fn with_verification_cache(mut self, capacity: usize) -> Self {
+        self.verification_cache = VerificationCache::new(capacity);
+        self
+    }
+
+    // Makes every mutating operation call `flush` before returning. It
+    // covers the same databases as `flush`.
+    pub fn with_flush_every_write(mut self, flush: bool) -> Self {
+        self.flush_every_write = flush;
+        self
+    }
+
+    // Flushes databases owned by this crate: index of issued vcs and
+    // metadata of default and named registries. KEL database of keri and
+    // event databases of teliox don't expose flush, so they are flushed by
+    // sled in background, every 500 ms by default, and when they are
+    // dropped.
+    pub fn flush(&self) -> Result<(), Error> {
+        self.tel.flush()?;
+        for tel in self.registries.read().unwrap().values() {
+            tel.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush_written(&self) -> Result<(), Error> {
+        if self.flush_every_write {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    // Hash of message used as vc identifier, for issuance and lookups.
+    fn message_hash(&self, message: &[u8]) -> SelfAddressingPrefix {
+        MessageHash::new_with(message, self.derivation.clone()).into()
+    }
+
+    // Returns issuer state from the moment of issuance of message with given
+    // hash, found using the source seal of issuance event. State is always
+    // computed from database, never taken from cached current state, so
+    // events processed from outside, e.g. with `respond`, are taken into
+    // account.
+    fn get_signing_state(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+    ) -> Result<IdentifierState, Error> {
+        let issuer_sn = self
+            .kerl
+            .get_state()?
+            .ok_or(Error::Generic("Identifier is not incepted".into()))?
+            .sn;
+        if let Some(state) = self.verification_cache.get(message_hash, issuer_sn) {
+            return Ok(state);
+        }
+        let state = self.get_signing_state_for(&self.tel.get_issuer()?, message_hash)?;
+        self.verification_cache
+            .insert(message_hash, issuer_sn, state.clone());
+        Ok(state)
+    }
+
+    // Re-anchored vcs are verified with state from their last re-anchoring.
+    fn get_signing_state_for(
+        &self,
+        issuer: &IdentifierPrefix,
+        message_hash: &SelfAddressingPrefix,
+    ) -> Result<IdentifierState, Error> {
+        resolve_signing_state(&self.kerl, &self.tel, issuer, message_hash)
+    }
+
+    // Returns verifier of signatures made with keys of vc with given hash,
+    // which resolves issuer state once for many signatures.
+    pub fn make_verifier(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+    ) -> Result<CredentialVerifier, Error> {
+        CredentialVerifier::new(
+            Arc::clone(&self.kerl),
+            Arc::clone(&self.tel),
+            message_hash.clone(),
+        )
+    }
+
+    // Returns serialized tel events of vc, one entry per event, so they can
+    // be parsed separately.
+    pub fn get_tel(&self, message_hash: MessageHash) -> Result<Vec<Vec<u8>>, Error> {
+        self.tel
+            .get_tel(&message_hash.into())?
             .iter()
-            .map(|e| e.serialize().unwrap())
-            .flatten()
-            .collect::<Vec<u8>>())
+            .map(|event| Ok(event.serialize()?))
+            .collect()
     }
 
     pub fn get_kerl(&self) -> Result<Option<Vec<u8>>, Error> {
         self.kerl.get_kerl()
     }
 
-    pub fn sign(&self, message: &Vec<u8>) -> Result<Vec<u8>, Error> {
-        self.key_manager.sign(&message).map_err(|e| e.into())
+    pub fn issuer_prefix(&self) -> IdentifierPrefix {
+        self.kerl.get_prefix()
+    }
+
+    // Returns prefix of management tel of controller's registry.
+    pub fn registry_prefix(&self) -> Result<IdentifierPrefix, Error> {
+        Ok(self.tel.get_management_tel_state()?.prefix)
+    }
+
+    // Returns keys of local identifier from its current KEL state.
+    pub fn current_keys(&self) -> Result<Vec<BasicPrefix>, Error> {
+        Ok(self
+            .kerl
+            .get_state()?
+            .ok_or(Error::Generic("Identifier is not incepted".into()))?
+            .current
+            .public_keys)
+    }
+
+    // Returns digests which commit to next keys of local identifier. KEL
+    // event commits to next keys with a single digest over next threshold
+    // and keys, so there is at most one. Abandoned identifier has none.
+    pub fn next_key_digests(&self) -> Result<Vec<SelfAddressingPrefix>, Error> {
+        Ok(self
+            .kerl
+            .get_state()?
+            .ok_or(Error::Generic("Identifier is not incepted".into()))?
+            .current
+            .threshold_key_digest
+            .into_iter()
+            .collect())
+    }
+
+    // Checks if local identifier still commits to next keys, so it can be
+    // rotated. Abandoned identifier has no next keys.
+    pub fn can_rotate(&self) -> Result<bool, Error> {
+        Ok(!self.next_key_digests()?.is_empty())
+    }
+
+    // Returns read only view of current KEL and TEL, see `Snapshot`. Waits
+    // for running updates, so tel events anchored in snapshot KEL are
+    // already processed.
+    pub fn snapshot(&self) -> Result<Snapshot, Error> {
+        let _commit = self.commit.write().unwrap();
+        Snapshot::new(Arc::clone(&self.kerl), Arc::clone(&self.tel))
+    }
+
+    // Returns counters of operations. Queue depth is always 0, since
+    // controller has no queue, see `Dispatcher::metrics`.
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.metrics.snapshot(0)
+    }
+
+    // Signature is indexed with position of key manager's key in current
+    // key list. Signing fails if the key isn't current, e.g. when key
+    // manager was rotated and KEL wasn't.
+    pub fn sign(&self, message: &Vec<u8>) -> Result<Signature, Error> {
+        let (key, code) = signing_key(self.key_manager.as_ref())?;
+        let index = self
+            .current_keys()?
+            .iter()
+            .position(|current| current == &key)
+            .ok_or_else(|| {
+                Error::Generic(format!(
+                    "Key {} of key manager is not current",
+                    key.to_str()
+                ))
+            })?;
+        let bytes = self
+            .key_manager
+            .sign(&message)
+            .map_err(Error::KeyManagerError)?;
+        Ok(Signature::new(code, index as u16, bytes))
+    }
+
+    // Signs message as member of multisig identifier, whose key is at
+    // `index` of current key list. Signatures of all members can be
+    // aggregated and verified together.
+    pub fn sign_at_index(
+        &self,
+        message: &[u8],
+        index: u16,
+    ) -> Result<AttachedSignaturePrefix, Error> {
+        let current_keys = self.current_keys()?;
+        let key = current_keys.get(index as usize).ok_or_else(|| {
+            Error::Generic(format!(
+                "Key index {} is out of range of {} current keys",
+                index,
+                current_keys.len()
+            ))
+        })?;
+        let (own_key, code) = signing_key(self.key_manager.as_ref())?;
+        if key != &own_key {
+            return Err(Error::Generic(format!(
+                "Key at index {} doesn't belong to key manager",
+                index
+            )));
+        }
+        let signature = self
+            .key_manager
+            .sign(&message.to_vec())
+            .map_err(Error::KeyManagerError)?;
+        Ok(AttachedSignaturePrefix::new(code, signature, index))
+    }
+}
+
+impl<K: ExportableKeyManager + Send + Sync> Controller<K> {
+    // Rotates issuer keys and registry backers with one KEL event. Events
+    // are made in order:
+    // 1. management tel rotation updating backers,
+    // 2. key manager rotation to its next keys,
+    // 3. KEL rotation event signed with new keys, which anchors seal of the
+    //    tel rotation,
+    // 4. tel rotation is processed with source seal of the KEL rotation.
+    // KEL rotation can be signed only with rotated keys, so if KEL doesn't
+    // accept it, key manager is restored from its seeds from before the
+    // rotation. Key manager can't be shared, since it's rotated in place.
+    pub fn rotate_and_update_backers(
+        &mut self,
+        ba: &[IdentifierPrefix],
+        br: &[IdentifierPrefix],
+    ) -> Result<(), Error> {
+        if self.kerl.is_abandoned()? {
+            return Err(Error::Abandoned);
+        }
+        let tel_rot = self.tel.make_rotation_event(ba, br)?;
+        let seal = to_event_seal(&tel_rot)?;
+
+        let _commit = self.commit.read().unwrap();
+        let key_manager = Arc::get_mut(&mut self.key_manager)
+            .ok_or(Error::Generic("Key manager is shared".into()))?;
+        let (current, next) = key_manager.seeds();
+        key_manager.rotate().map_err(Error::KeyManagerError)?;
+        let kel_rot = match self.kerl.rotate_with_seal(&*key_manager, &[seal]) {
+            Ok(kel_rot) => kel_rot,
+            Err(e) => {
+                *key_manager = K::from_seeds(&current, &next)?;
+                return Err(e);
+            }
+        };
+        let source_seal = to_source_seal(&kel_rot)?;
+        if let Err(e) = self.tel.process(tel_rot.clone(), source_seal.clone()) {
+            let kel_sn = source_seal.sn;
+            warn!(
+                "Tel event anchored at KEL sn {} failed to be processed, kept for repair: {}",
+                kel_sn, e
+            );
+            self.orphaned.lock().unwrap().push((tel_rot, source_seal));
+            return Err(Error::Inconsistent { kel_sn });
+        }
+        self.notify(&tel_rot);
+        self.flush_written()
+    }
+
+    // Returns current and next seeds of key manager encrypted under the key
+    // derived from `passphrase`.
+    pub fn export_key_material(&self, passphrase: &str) -> Result<EncryptedSeed, Error> {
+        EncryptedSeed::encrypt(self.key_manager.as_ref(), passphrase)
+    }
+
+    pub fn load_with_key_material(
+        key_material: &EncryptedSeed,
+        passphrase: &str,
+        db_dir_path: &Path,
+    ) -> Result<Self, Error> {
+        let km = key_material.decrypt(passphrase)?;
+        Controller::load(km, db_dir_path)
+    }
+}
+
+const KEL_DIR: &str = "kel";
+const TEL_DIR: &str = "tel";
+// Directory of named registries databases, each in subdirectory named after
+// registry.
+const REGISTRIES_DIR: &str = "registries";
+// File with prefix of registry, kept in registry database directory.
+const REGISTRY_PREFIX: &str = "registry_prefix";
+
+// Opens databases of named registries made with `create_registry`.
+fn load_registries(db_dir_path: &Path) -> Result<HashMap<String, Arc<Tel>>, Error> {
+    let mut registries = HashMap::new();
+    let registries_path = db_dir_path.join(REGISTRIES_DIR);
+    if !registries_path.exists() {
+        return Ok(registries);
+    }
+    for entry in std::fs::read_dir(registries_path)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or(Error::Generic("Invalid registry directory".into()))?
+            .to_string();
+        let prefix = std::fs::read_to_string(path.join(REGISTRY_PREFIX))?.parse()?;
+        registries.insert(name, Arc::new(Tel::load(&path, prefix)?));
     }
+    Ok(registries)
 }
 
-fn to_event_seal(event: &Event) -> Result<Seal, Error> {
+// Marks database directories created after the kel and tel directories were
+// swapped back to match their names.
+const LAYOUT_MARKER: &str = "layout_v1";
+
+// Returns paths of kel and tel databases inside `db_dir_path`. Directories
+// created before layout marker was introduced keep kel in `tel` directory
+// and tel in `kel` directory, so they are opened swapped.
+pub fn resolve_db_paths(db_dir_path: &Path) -> (PathBuf, PathBuf) {
+    let kel_path = db_dir_path.join(KEL_DIR);
+    let tel_path = db_dir_path.join(TEL_DIR);
+    if is_legacy_layout(db_dir_path) {
+        (tel_path, kel_path)
+    } else {
+        (kel_path, tel_path)
+    }
+}
+
+pub fn is_legacy_layout(db_dir_path: &Path) -> bool {
+    !db_dir_path.join(LAYOUT_MARKER).exists()
+        && db_dir_path.join(KEL_DIR).exists()
+        && db_dir_path.join(TEL_DIR).exists()
+}
+
+pub(crate) fn to_event_seal(event: &Event) -> Result<Seal, Error> {
     Ok(Seal::Event(EventSeal {
         prefix: event.get_prefix(),
         sn: event.get_sn(),
-        event_digest: SelfAddressing::Blake3_256.derive(&event.serialize()?),
+        event_digest: SelfAddressing::Blake3_256.derive(&canonical_bytes(event)?),
     }))
 }
 
-fn to_source_seal(event_message: &EventMessage) -> Result<EventSourceSeal, Error> {
+// Returns issuer state from the moment of issuance of vc, or its last
+// re-anchoring.
+fn resolve_signing_state(
+    kerl: &KERL,
+    tel: &Tel,
+    issuer: &IdentifierPrefix,
+    message_hash: &SelfAddressingPrefix,
+) -> Result<IdentifierState, Error> {
+    let source_seal = match tel.get_reanchored(message_hash)? {
+        Some(seal) => seal,
+        None => {
+            tel.get_tel(message_hash)?
+                .into_iter()
+                .next()
+                .ok_or(Error::Generic("Missing issuance event".into()))?
+                .seal
+                .seal
+        }
+    };
+    signing_state_at_seal(kerl, issuer, &source_seal)
+}
+
+// Returns issuer state after KEL event with given source seal.
+fn signing_state_at_seal(
+    kerl: &KERL,
+    issuer: &IdentifierPrefix,
+    source_seal: &EventSourceSeal,
+) -> Result<IdentifierState, Error> {
+    let state = kerl
+        .get_state_for_seal(issuer, source_seal.sn, &source_seal.digest)?
+        .ok_or(Error::Generic("Missing issuer state".into()))?;
+    // Witnessed issuer's anchoring event counts only when enough of its
+    // witnesses receipted it.
+    if state.tally > 0 {
+        let received = kerl.witness_receipt_count(issuer, source_seal.sn, &state.witnesses)? as u64;
+        if received < state.tally {
+            return Err(Error::InsufficientReceipts {
+                required: state.tally,
+                received,
+            });
+        }
+    }
+    Ok(state)
+}
+
+// Source seal digest is always computed over the anchoring event message
+// without attached signatures, the same bytes which are kept as `last` in
+// issuer's state.
+pub(crate) fn to_source_seal(
+    anchoring_event: &SignedEventMessage,
+) -> Result<EventSourceSeal, Error> {
+    message_source_seal(&anchoring_event.event_message)
+}
+
+fn message_source_seal(event_message: &EventMessage) -> Result<EventSourceSeal, Error> {
     Ok(EventSourceSeal {
         sn: event_message.event.sn,
         digest: SelfAddressing::Blake3_256.derive(&event_message.serialize()?),
@@ -167,38 +1393,90 @@ pub struct Dispatcher<K: KeyManager + Send + Sync + 'static> {
 
 impl<K: KeyManager + Send + Sync> Dispatcher<K> {
     pub fn init(km: K, db_dir_path: &Path) -> Result<Self, Error> {
-        Ok(Dispatcher {
-            controller: Arc::new(RwLock::new(Controller::init(km, db_dir_path)?)),
-            // TODO remove magic number
-            task_manager: Arc::new(TaskManager::new(5)),
-        })
+        Ok(Dispatcher::with_queue(
+            Controller::init(km, db_dir_path)?,
+            DEFAULT_QUEUE_CAPACITY,
+        ))
     }
 
-    pub fn issue(&self, msg: String, sender: Sender<HandleResult>) -> Result<(), Error> {
+    pub fn load(km: K, db_dir_path: &Path) -> Result<Self, Error> {
+        Ok(Dispatcher::with_queue(
+            Controller::load(km, db_dir_path)?,
+            DEFAULT_QUEUE_CAPACITY,
+        ))
+    }
+
+    // Task manager of dispatcher updates the same counters as controller.
+    pub(crate) fn with_queue(controller: Controller<K>, queue_capacity: usize) -> Self {
+        let task_manager = TaskManager::new(queue_capacity, DEFAULT_POLL_INTERVAL)
+            .with_metrics(Arc::clone(&controller.metrics));
+        Dispatcher {
+            controller: Arc::new(RwLock::new(controller)),
+            task_manager: Arc::new(task_manager),
+        }
+    }
+
+    // Returns counters of controller operations and handled tasks, along
+    // with number of tasks waiting in queue. Doesn't wait for running tasks.
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.task_manager.metrics()
+    }
+
+    pub fn issue(&self, msg: String, sender: Sender<TaskResponse>) -> Result<u64, Error> {
         let task = IssueTask::new(msg, Arc::clone(&self.controller));
         self.task_manager.push(Box::new(task), sender)
     }
 
-    pub fn revoke(&self, msg_hash: String, sender: Sender<HandleResult>) -> Result<(), Error> {
+    pub fn revoke(&self, msg_hash: String, sender: Sender<TaskResponse>) -> Result<u64, Error> {
         let task = RevokeTask::new(msg_hash, Arc::clone(&self.controller));
         self.task_manager.push(Box::new(task), sender)
     }
 
-    pub fn get_kel(&self, sender: Sender<HandleResult>) -> Result<(), Error> {
+    // Sets derivation of hashes of issued messages, see
+    // `Controller::with_derivation`.
+    pub fn with_derivation(self, derivation: SelfAddressing) -> Self {
+        self.controller.write().unwrap().derivation = derivation;
+        self
+    }
+
+    // Sets size of verification cache, see
+    // `Controller::with_verification_cache`.
+    pub fn with_verification_cache(self, capacity: usize) -> Self {
+        self.controller.write().unwrap().verification_cache = VerificationCache::new(capacity);
+        self
+    }
+
+    pub fn update_backers(
+        &self,
+        add: Vec<IdentifierPrefix>,
+        remove: Vec<IdentifierPrefix>,
+        sender: Sender<TaskResponse>,
+    ) -> Result<u64, Error> {
+        let task = UpdateBackersTask::new(add, remove, Arc::clone(&self.controller));
+        self.task_manager.push(Box::new(task), sender)
+    }
+
+    pub fn get_kel(&self, sender: Sender<TaskResponse>) -> Result<u64, Error> {
         let task = GetKelTask::new(Arc::clone(&self.controller));
         self.task_manager.push(Box::new(task), sender)
     }
 
-    pub fn get_tel(&self, msg: MessageHash, sender: Sender<HandleResult>) -> Result<(), Error> {
+    pub fn get_tel(&self, msg: MessageHash, sender: Sender<TaskResponse>) -> Result<u64, Error> {
         let task = GetTelTask::new(Arc::clone(&self.controller), msg);
         self.task_manager.push(Box::new(task), sender)
     }
 
-    pub fn sign(&self, msg: Vec<u8>, sender: Sender<HandleResult>) -> Result<(), Error> {
+    pub fn sign(&self, msg: Vec<u8>, sender: Sender<TaskResponse>) -> Result<u64, Error> {
         let task = SignMessageTask::new(Arc::clone(&self.controller), msg);
         self.task_manager.push(Box::new(task), sender)
     }
 
+    // Lock is held only to clone database handles, queries on returned
+    // snapshot don't wait for running tasks.
+    pub fn snapshot(&self) -> Result<Snapshot, Error> {
+        self.controller.read().unwrap().snapshot()
+    }
+
     pub fn listen(&self) -> Result<(), Error> {
         TaskManager::listen(Arc::clone(&self.task_manager))?;
         Ok(())
@@ -207,13 +1485,11 @@ impl<K: KeyManager + Send + Sync> Dispatcher<K> {
 
 #[test]
 pub fn test_responses() -> Result<(), Error> {
+    use crate::{task::HandleResult, test_support::fresh_dispatcher};
     use crossbeam_channel::bounded;
-    use keri::signer::CryptoBox;
-    use tempfile::tempdir;
 
-    let dir = tempdir().unwrap();
-    let km = CryptoBox::new().unwrap();
-    let controller = Arc::new(Dispatcher::init(km, dir.path())?);
+    let (dispatcher, _dir) = fresh_dispatcher();
+    let controller = Arc::new(dispatcher);
 
     let c = Arc::clone(&controller);
     c.listen()?;
@@ -221,17 +1497,2474 @@ pub fn test_responses() -> Result<(), Error> {
     let cont = Arc::clone(&controller);
     let (s1, r1) = bounded(0);
     cont.issue("vc2".to_owned(), s1.clone()).unwrap();
-    assert!(matches!(r1.recv(), Ok(HandleResult::Issued(_))));
+    assert!(matches!(
+        r1.recv().map(|r| r.result),
+        Ok(HandleResult::Issued(_))
+    ));
 
     let cont = Arc::clone(&controller);
     let (s2, r2) = bounded(0);
     cont.sign("msg".as_bytes().to_vec(), s2).unwrap();
-    assert!(matches!(r2.recv(), Ok(HandleResult::MessageSigned(_))));
+    assert!(matches!(
+        r2.recv().map(|r| r.result),
+        Ok(HandleResult::MessageSigned(_))
+    ));
 
     let cont = Arc::clone(&controller);
     let (s3, r3) = bounded(0);
     cont.get_kel(s3.clone()).unwrap();
-    assert!(matches!(r3.recv(), Ok(HandleResult::GotKel(_))));
+    assert!(matches!(
+        r3.recv().map(|r| r.result),
+        Ok(HandleResult::GotKel(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_update_signing_failure() -> Result<(), Error> {
+    use crate::test_support::FailingKeyManager;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let fail = Arc::new(AtomicBool::new(false));
+    let km = FailingKeyManager::new(Arc::clone(&fail));
+    let controller = Controller::init(km, dir.path())?;
+
+    fail.store(true, Ordering::SeqCst);
+    let result = controller.update(UpdateType::Issue("vc".into()));
+    assert!(matches!(result, Err(Error::KeyManagerError(_))));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_db_layout() -> Result<(), Error> {
+    use keri::{database::sled::SledEventDatabase, processor::EventProcessor, signer::CryptoBox};
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let prefix = {
+        let controller = Controller::init(CryptoBox::new()?, dir.path())?;
+        controller.kerl.get_prefix()
+    };
+    assert!(!is_legacy_layout(dir.path()));
+    assert_eq!(
+        resolve_db_paths(dir.path()),
+        (dir.path().join("kel"), dir.path().join("tel"))
+    );
+    let kel_db = SledEventDatabase::new(&dir.path().join("kel"))?;
+    assert!(EventProcessor::new(&kel_db).get_kerl(&prefix)?.is_some());
+
+    // Directory without layout marker has swapped databases.
+    let legacy_dir = tempdir().unwrap();
+    std::fs::create_dir(legacy_dir.path().join("kel"))?;
+    std::fs::create_dir(legacy_dir.path().join("tel"))?;
+    assert!(is_legacy_layout(legacy_dir.path()));
+    assert_eq!(
+        resolve_db_paths(legacy_dir.path()),
+        (legacy_dir.path().join("tel"), legacy_dir.path().join("kel"))
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn test_load() -> Result<(), Error> {
+    use keri::{error::Error as KeriError, keys::PublicKey, signer::CryptoBox};
+    use tempfile::tempdir;
+
+    // Key manager which can be passed to more than one controller.
+    struct SharedKeyManager(Arc<CryptoBox>);
+
+    impl KeyManager for SharedKeyManager {
+        fn sign(&self, msg: &Vec<u8>) -> Result<Vec<u8>, KeriError> {
+            self.0.sign(msg)
+        }
+
+        fn public_key(&self) -> PublicKey {
+            self.0.public_key()
+        }
+
+        fn next_public_key(&self) -> PublicKey {
+            self.0.next_public_key()
+        }
+
+        fn rotate(&mut self) -> Result<(), KeriError> {
+            Err(KeriError::SemanticError("Can't rotate shared keys".into()))
+        }
+    }
+
+    let km = Arc::new(CryptoBox::new()?);
+    let dir = tempdir().unwrap();
+    let (prefix, first_vc) = {
+        let controller = Controller::init(SharedKeyManager(Arc::clone(&km)), dir.path())?;
+        controller.update(UpdateType::Issue("vc1".into()))?;
+        (
+            controller.kerl.get_prefix(),
+            MessageHash::new("vc1".as_bytes()),
+        )
+    };
+
+    // Directory already contains KEL.
+    assert!(Controller::init(CryptoBox::new()?, dir.path()).is_err());
+
+    let controller = Controller::load(SharedKeyManager(km), dir.path())?;
+    assert_eq!(controller.kerl.get_prefix(), prefix);
+    assert!(matches!(
+        controller.tel.get_vc_state(&first_vc.into())?,
+        TelState::Issued(_)
+    ));
+
+    controller.update(UpdateType::Issue("vc2".into()))?;
+    assert!(matches!(
+        controller
+            .tel
+            .get_vc_state(&MessageHash::new("vc2".as_bytes()).into())?,
+        TelState::Issued(_)
+    ));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_key_material() -> Result<(), Error> {
+    use crate::key_manager::seeded::SeededKeyManager;
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let key_material = {
+        let controller = Controller::init(SeededKeyManager::new(), dir.path())?;
+        controller.update(UpdateType::Issue("vc1".into()))?;
+        controller.export_key_material("passphrase")?
+    };
+
+    assert!(Controller::<SeededKeyManager>::load_with_key_material(
+        &key_material,
+        "wrong passphrase",
+        dir.path()
+    )
+    .is_err());
+
+    let controller = Controller::<SeededKeyManager>::load_with_key_material(
+        &key_material,
+        "passphrase",
+        dir.path(),
+    )?;
+    // Event signed with restored keys is accepted by KEL.
+    controller.update(UpdateType::Issue("vc2".into()))?;
+    assert!(matches!(
+        controller
+            .tel
+            .get_vc_state(&MessageHash::new("vc2".as_bytes()).into())?,
+        TelState::Issued(_)
+    ));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_verify() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+    use keri::derivation::self_signing::SelfSigning;
+
+    let (controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc".into()))?;
+
+    let signature = controller.sign(&"vc".as_bytes().to_vec())?;
+    assert!(controller.verify("vc", &signature)?);
+
+    // Signature sent over the wire.
+    let received = Signature::from_bytes(&signature.to_bytes())?;
+    assert!(controller.verify("vc", &received)?);
+
+    // Signature of other message.
+    let other_signature = controller.sign(&"other vc".as_bytes().to_vec())?;
+    assert!(!controller.verify("vc", &other_signature)?);
+
+    // Malformed signature.
+    assert!(!controller.verify(
+        "vc",
+        &Signature::new(SelfSigning::Ed25519Sha512, 0, vec![0u8; 5])
+    )?);
+
+    // Message wasn't issued.
+    assert!(!controller.verify("other vc", &other_signature)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_verify_bytes() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    let message = vec![0xff, 0xfe, 0x00, 0x80];
+    assert!(String::from_utf8(message.clone()).is_err());
+    controller.update(UpdateType::IssueBytes(message.clone()))?;
+
+    let signature = controller.sign(&message)?;
+    assert!(controller.verify_bytes(&message, &signature)?);
+    assert!(!controller.verify_bytes(&[0xff, 0xfe], &signature)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_write_logs() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc".into()))?;
+    let message_hash = MessageHash::new("vc".as_bytes());
+    controller.update(UpdateType::Revoke(message_hash.clone()))?;
+
+    let mut kel = vec![];
+    controller.kerl.write_kerl(&mut kel)?;
+    assert_eq!(Some(kel), controller.get_kerl()?);
+
+    let mut tel = vec![];
+    controller
+        .tel
+        .write_tel(&message_hash.clone().into(), &mut tel)?;
+    assert_eq!(tel, controller.get_tel(message_hash)?.concat());
+
+    Ok(())
+}
+
+#[test]
+pub fn test_iter_tel() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc".into()))?;
+    let message_hash = MessageHash::new("vc".as_bytes());
+    controller.update(UpdateType::Revoke(message_hash.clone()))?;
+
+    let message_hash = message_hash.into();
+    let mut visited = 0;
+    let revocation = controller
+        .tel
+        .iter_tel(&message_hash)
+        .inspect(|_| visited += 1)
+        .find(|event| matches!(event, Ok(ev) if is_revocation(&ev.event)));
+    assert_eq!(visited, 2);
+    assert_eq!(revocation.unwrap()?.event.get_sn(), 1);
+
+    // Iterator stops before reaching revocation.
+    let mut visited = 0;
+    let issuance = controller
+        .tel
+        .iter_tel(&message_hash)
+        .inspect(|_| visited += 1)
+        .find(|event| matches!(event, Ok(ev) if !is_revocation(&ev.event)));
+    assert_eq!(visited, 1);
+    assert_eq!(issuance.unwrap()?.event.get_sn(), 0);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_anchor_tel_events() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc1".into()))?;
+    let vc1_hash = MessageHash::new("vc1".as_bytes());
+    let vc2_hash = MessageHash::new("vc2".as_bytes());
+
+    let iss = controller.tel.make_issuance_event("vc2".as_bytes())?;
+    let rev = controller.tel.make_revoke_event(&vc1_hash.to_string())?;
+    let ixn = controller.anchor_tel_events(&[iss.clone(), rev.clone()])?;
+    match &ixn.event_message.event.event_data {
+        EventData::Ixn(ixn) => {
+            assert_eq!(ixn.data, vec![to_event_seal(&iss)?, to_event_seal(&rev)?])
+        }
+        _ => panic!("Unexpected event type"),
+    };
+
+    let source_seal = to_source_seal(&ixn)?;
+    controller.tel.process(iss, source_seal.clone())?;
+    controller.tel.process(rev, source_seal)?;
+    assert!(matches!(
+        controller.tel.get_vc_state(&vc2_hash.into())?,
+        TelState::Issued(_)
+    ));
+    assert!(matches!(
+        controller.tel.get_vc_state(&vc1_hash.into())?,
+        TelState::Revoked
+    ));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_source_seals_consistency() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc1".into()))?;
+    controller.update(UpdateType::Issue("vc2".into()))?;
+    controller.update(UpdateType::Revoke(MessageHash::new("vc1".as_bytes())))?;
+
+    let issuer = controller.tel.get_issuer()?;
+    for vc in &["vc1", "vc2"] {
+        for event in controller
+            .tel
+            .get_tel(&MessageHash::new(vc.as_bytes()).into())?
+        {
+            let source_seal = event.seal.seal;
+            // Source seal digest matches anchoring event.
+            assert!(controller
+                .kerl
+                .get_state_for_seal(&issuer, source_seal.sn, &source_seal.digest)?
+                .is_some());
+            // Anchoring event contains seal of tel event.
+            assert!(controller
+                .kerl
+                .check_seal(source_seal.sn, &issuer, &event.event)?);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn test_process_verified() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc1".into()))?;
+
+    // Issuance event with source seal of event which anchors other issuance.
+    let forged = controller.tel.make_issuance_event("forged".as_bytes())?;
+    let vc1_source_seal = controller
+        .tel
+        .get_tel(&MessageHash::new("vc1".as_bytes()).into())?[0]
+        .seal
+        .seal
+        .clone();
+    assert!(controller
+        .tel
+        .process_verified(forged, vc1_source_seal, &controller.kerl)
+        .is_err());
+    assert!(!matches!(
+        controller
+            .tel
+            .get_vc_state(&MessageHash::new("forged".as_bytes()).into())?,
+        TelState::Issued(_)
+    ));
+
+    let iss = controller.tel.make_issuance_event("vc2".as_bytes())?;
+    let ixn = controller.anchor_tel_events(std::slice::from_ref(&iss))?;
+    controller
+        .tel
+        .process_verified(iss, to_source_seal(&ixn)?, &controller.kerl)?;
+    assert!(matches!(
+        controller
+            .tel
+            .get_vc_state(&MessageHash::new("vc2".as_bytes()).into())?,
+        TelState::Issued(_)
+    ));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_revoke_batch() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    let hashes: Vec<SelfAddressingPrefix> = ["vc1", "vc2", "vc3"]
+        .iter()
+        .map(|vc| -> Result<_, Error> {
+            controller.update(UpdateType::Issue(vc.to_string()))?;
+            Ok(MessageHash::new(vc.as_bytes()).into())
+        })
+        .collect::<Result<_, _>>()?;
+    controller.update(UpdateType::Revoke(MessageHash::new(b"vc2")))?;
+    let kel_sn = controller.kerl.get_state()?.unwrap().sn;
+
+    let report = controller.revoke_batch(&hashes)?;
+    assert_eq!(report.revoked, vec![hashes[0].clone(), hashes[2].clone()]);
+    assert_eq!(
+        report.skipped,
+        vec![(hashes[1].clone(), VcStatusKind::Revoked)]
+    );
+
+    // Both revocations are anchored in one interaction event.
+    assert_eq!(controller.kerl.get_state()?.unwrap().sn, kel_sn + 1);
+    for hash in &report.revoked {
+        assert!(matches!(
+            controller.tel.get_vc_state(hash)?,
+            TelState::Revoked
+        ));
+        assert_eq!(controller.tel.get_tel(hash)?[1].seal.seal.sn, kel_sn + 1);
+    }
+
+    // Nothing to revoke, nothing is anchored.
+    let report = controller.revoke_batch(&hashes)?;
+    assert!(report.revoked.is_empty());
+    assert_eq!(report.skipped.len(), 3);
+    assert_eq!(controller.kerl.get_state()?.unwrap().sn, kel_sn + 1);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_tel_ingest() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+    use tempfile::tempdir;
+
+    let (controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc".into()))?;
+    let message_hash: SelfAddressingPrefix = MessageHash::new("vc".as_bytes()).into();
+
+    let verifier_kel_dir = tempdir().unwrap();
+    let verifier_kerl = KERL::new(verifier_kel_dir.path())?;
+    verifier_kerl.process_kel(&controller.get_kerl()?.unwrap())?;
+    let verifier_tel_dir = tempdir().unwrap();
+    let mut verifier_tel = Tel::new(verifier_tel_dir.path())?;
+
+    let management_tel = controller.tel.get_management_events()?.unwrap();
+    assert!(verifier_tel
+        .ingest(&management_tel, &verifier_kerl)?
+        .is_empty());
+
+    let mut vc_tel = vec![];
+    controller.tel.write_tel(&message_hash, &mut vc_tel)?;
+    let updated = verifier_tel.ingest(&vc_tel, &verifier_kerl)?;
+    assert_eq!(updated, vec![message_hash.clone()]);
+
+    let mut ingested_tel = vec![];
+    verifier_tel.write_tel(&message_hash, &mut ingested_tel)?;
+    assert_eq!(ingested_tel, vc_tel);
+    assert!(matches!(
+        verifier_tel.get_vc_state(&message_hash)?,
+        TelState::Issued(_)
+    ));
+
+    // Malformed stream.
+    assert!(matches!(
+        verifier_tel.ingest(&vc_tel[..vc_tel.len() / 2], &verifier_kerl),
+        Err(Error::Parse(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_tampered_source_seal() -> Result<(), Error> {
+    use crate::{
+        controller::verifier::verify_with_kel, tel::parse_tel_stream,
+        test_support::fresh_controller,
+    };
+    use teliox::event::verifiable_event::VerifiableEvent;
+    use tempfile::tempdir;
+
+    let (controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc".into()))?;
+    let signature = controller.sign(&b"vc".to_vec())?;
+    let message_hash: SelfAddressingPrefix = MessageHash::new(b"vc").into();
+
+    // Source seal points to the right KEL event, but with wrong digest.
+    let issuance = controller.tel.get_tel(&message_hash)?.remove(0);
+    let tampered_seal = EventSourceSeal {
+        sn: issuance.seal.seal.sn,
+        digest: SelfAddressing::Blake3_256.derive(b"tampered"),
+    };
+    let kel = controller.get_kerl()?.unwrap();
+    let mut tel = controller.tel.get_management_events()?.unwrap();
+    tel.extend(
+        VerifiableEvent::new(issuance.event.clone(), tampered_seal.clone().into()).serialize()?,
+    );
+
+    let verifier_dir = tempdir().unwrap();
+    let (kel_path, tel_path) = resolve_db_paths(verifier_dir.path());
+    let verifier_kerl = KERL::new(&kel_path)?;
+    verifier_kerl.process_kel(&kel)?;
+    let mut verifier_tel = Tel::new(&tel_path)?;
+    match verifier_tel.ingest(&tel, &verifier_kerl) {
+        Err(Error::RejectedEvents(rejected)) => assert_eq!(rejected.len(), 1),
+        _ => panic!("Expected rejected issuance"),
+    }
+    assert!(verify_with_kel(&kel, &tel, b"vc", &signature, SelfAddressing::Blake3_256).is_err());
+
+    // Tel processed without checks keeps tampered seal, which fails key
+    // resolution.
+    let unchecked_dir = tempdir().unwrap();
+    let unchecked_tel = Tel::new(unchecked_dir.path())?;
+    for event in parse_tel_stream(&controller.tel.get_management_events()?.unwrap())? {
+        unchecked_tel.process(event.event, event.seal.seal)?;
+    }
+    unchecked_tel.process(issuance.event, tampered_seal)?;
+    assert!(matches!(
+        resolve_signing_state(
+            &verifier_kerl,
+            &unchecked_tel,
+            &controller.kerl.get_prefix(),
+            &message_hash
+        ),
+        Err(Error::Anchoring(AnchoringFailure::SourceSealMismatch(_)))
+    ));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_tel_fork() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+    use keri::{derivation::basic::Basic, signer::CryptoBox};
+    use teliox::event::verifiable_event::VerifiableEvent;
+    use tempfile::tempdir;
+
+    let (controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc".into()))?;
+    let message_hash: SelfAddressingPrefix = MessageHash::new("vc".as_bytes()).into();
+
+    // Two replicas revoke the same vc. Registry rotation between them makes
+    // the revocations differ. Neither is processed by the issuer's tel.
+    let anchor_revocation = || -> Result<Vec<u8>, Error> {
+        let rev = controller.tel.make_revoke_event(&message_hash.to_str())?;
+        let ixn = controller
+            .kerl
+            .make_ixn_with_seal(&[to_event_seal(&rev)?], &*controller.key_manager)?;
+        Ok(VerifiableEvent::new(rev, to_source_seal(&ixn)?.into()).serialize()?)
+    };
+    let first_revocation = anchor_revocation()?;
+    let backer = IdentifierPrefix::Basic(Basic::Ed25519.derive(CryptoBox::new()?.public_key()));
+    controller.update_backers(&[backer], &[])?;
+    let second_revocation = anchor_revocation()?;
+    assert_ne!(first_revocation, second_revocation);
+
+    let verifier_kel_dir = tempdir().unwrap();
+    let verifier_kerl = KERL::new(verifier_kel_dir.path())?;
+    verifier_kerl.process_kel(&controller.get_kerl()?.unwrap())?;
+    let verifier_tel_dir = tempdir().unwrap();
+    let mut verifier_tel = Tel::new(verifier_tel_dir.path())?;
+    verifier_tel.ingest(
+        &controller.tel.get_management_events()?.unwrap(),
+        &verifier_kerl,
+    )?;
+    let mut vc_tel = vec![];
+    controller.tel.write_tel(&message_hash, &mut vc_tel)?;
+    verifier_tel.ingest(&vc_tel, &verifier_kerl)?;
+
+    verifier_tel.ingest(&first_revocation, &verifier_kerl)?;
+    assert!(verifier_tel.detect_fork(&message_hash)?.is_none());
+    assert_eq!(
+        verifier_tel.ingest(&second_revocation, &verifier_kerl)?,
+        vec![message_hash.clone()]
+    );
+
+    let fork = verifier_tel.detect_fork(&message_hash)?.unwrap();
+    assert_eq!(fork.sn, 1);
+    assert_eq!(fork.stored.serialize()?, first_revocation);
+    assert_eq!(fork.conflicting.serialize()?, second_revocation);
+    assert!(matches!(
+        verifier_tel.get_vc_state(&message_hash),
+        Err(Error::Forked { sn: 1 })
+    ));
+
+    // Revocation anchored earlier in issuer's KEL wins.
+    assert_eq!(fork.preferred()?.serialize()?, first_revocation);
+    verifier_tel.resolve_fork(&message_hash)?;
+    assert!(verifier_tel.detect_fork(&message_hash)?.is_none());
+    assert!(matches!(
+        verifier_tel.get_vc_state(&message_hash)?,
+        TelState::Revoked
+    ));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_concurrent_issuance() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    let controller = Arc::new(controller);
+
+    let handles: Vec<_> = (0..8)
+        .map(|thread| {
+            let controller = Arc::clone(&controller);
+            std::thread::spawn(move || -> Result<(), Error> {
+                for i in 0..5 {
+                    controller.update(UpdateType::Issue(format!("vc{}-{}", thread, i)))?;
+                }
+                Ok(())
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap()?;
+    }
+
+    for thread in 0..8 {
+        for i in 0..5 {
+            let message = format!("vc{}-{}", thread, i);
+            assert!(matches!(
+                controller
+                    .tel
+                    .get_vc_state(&MessageHash::new(message.as_bytes()).into())?,
+                TelState::Issued(_)
+            ));
+        }
+    }
+    // Inception, tel inception and at most one interaction event per
+    // issuance. Batching itself is checked in `anchoring::test_anchor_batch`.
+    assert!(controller.kerl.get_state()?.unwrap().sn <= 41);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_snapshot() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let (controller, _dir) = fresh_controller();
+    let controller = Arc::new(controller);
+    let done = Arc::new(AtomicBool::new(false));
+
+    let issuer = {
+        let (controller, done) = (Arc::clone(&controller), Arc::clone(&done));
+        std::thread::spawn(move || -> Result<(), Error> {
+            for i in 0..20 {
+                controller.update(UpdateType::Issue(format!("vc{}", i)))?;
+            }
+            done.store(true, Ordering::SeqCst);
+            Ok(())
+        })
+    };
+
+    let verifier = {
+        let (controller, done) = (Arc::clone(&controller), Arc::clone(&done));
+        std::thread::spawn(move || -> Result<(), Error> {
+            while !done.load(Ordering::SeqCst) {
+                let snapshot = controller.snapshot()?;
+                let kel = snapshot.get_kel()?;
+                for i in 0..20 {
+                    let hash = MessageHash::new(format!("vc{}", i).as_bytes()).into();
+                    let tel = snapshot.get_tel(&hash)?;
+                    // Vc visible in snapshot is anchored in its KEL.
+                    for event in &tel {
+                        assert!(event.seal.seal.sn <= snapshot.sn());
+                    }
+                    // Repeated reads of snapshot give the same result.
+                    assert_eq!(snapshot.get_tel(&hash)?.len(), tel.len());
+                    assert_eq!(
+                        matches!(snapshot.get_vc_state(&hash)?, TelState::Issued(_)),
+                        !tel.is_empty()
+                    );
+                }
+                assert_eq!(snapshot.get_kel()?, kel);
+            }
+            Ok(())
+        })
+    };
+
+    issuer.join().unwrap()?;
+    verifier.join().unwrap()?;
+
+    let snapshot = controller.snapshot()?;
+    for i in 0..20 {
+        let hash = MessageHash::new(format!("vc{}", i).as_bytes()).into();
+        assert!(matches!(snapshot.get_vc_state(&hash)?, TelState::Issued(_)));
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn test_prefixes() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+
+    let issuer_prefix = controller.issuer_prefix();
+    let registry_prefix = controller.registry_prefix()?;
+    assert_ne!(issuer_prefix, IdentifierPrefix::default());
+    assert_ne!(registry_prefix, IdentifierPrefix::default());
+    assert_ne!(issuer_prefix, registry_prefix);
+    assert_eq!(controller.tel.get_issuer()?, issuer_prefix);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_get_pub_key_for() -> Result<(), Error> {
+    use crate::{tel::parse_tel_stream, test_support::fresh_controller};
+    use keri::derivation::basic::Basic;
+
+    let (a, _dir_a) = fresh_controller();
+    let (b, _dir_b) = fresh_controller();
+    a.update(UpdateType::Issue("vc a".into()))?;
+    b.update(UpdateType::Issue("vc b".into()))?;
+    let (hash_a, hash_b) = (
+        MessageHash::new("vc a".as_bytes()).into(),
+        MessageHash::new("vc b".as_bytes()).into(),
+    );
+
+    // Issuer's KEL is not known yet.
+    assert!(a.get_pub_key_for(&b.issuer_prefix(), &hash_b).is_err());
+
+    // Put KEL and TELs of b into a's store.
+    a.kerl.process_kel(&b.get_kerl()?.unwrap())?;
+    let b_events = parse_tel_stream(&b.tel.get_management_events()?.unwrap())?
+        .into_iter()
+        .chain(b.tel.get_tel(&hash_b)?);
+    for event in b_events {
+        a.tel.process(event.event, event.seal.seal)?;
+    }
+
+    let key_a = Basic::Ed25519.derive(a.key_manager.public_key());
+    let key_b = Basic::Ed25519.derive(b.key_manager.public_key());
+    assert_eq!(a.get_pub_key(&hash_a)?, vec![key_a.clone()]);
+    assert_eq!(a.get_pub_key_for(&a.issuer_prefix(), &hash_a)?, vec![key_a]);
+    assert_eq!(a.get_pub_key_for(&b.issuer_prefix(), &hash_b)?, vec![key_b]);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_abandon() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (mut controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc".into()))?;
+    let signature = controller.sign(&"vc".as_bytes().to_vec())?;
+    assert!(controller.verify("vc", &signature)?);
+    let issuance_sn = controller.kerl.get_state()?.unwrap().sn;
+
+    let km = Arc::get_mut(&mut controller.key_manager).unwrap();
+    km.rotate()?;
+    controller.kerl.abandon(controller.key_manager.as_ref())?;
+    assert!(controller.kerl.is_abandoned()?);
+
+    assert!(matches!(
+        controller.kerl.rotate(controller.key_manager.as_ref()),
+        Err(Error::Abandoned)
+    ));
+    assert!(matches!(
+        controller.kerl.abandon(controller.key_manager.as_ref()),
+        Err(Error::Abandoned)
+    ));
+    assert!(matches!(
+        controller.update(UpdateType::Issue("vc2".into())),
+        Err(Error::Abandoned)
+    ));
+    // Vcs issued before abandonment are still verified.
+    assert!(controller.verify("vc", &signature)?);
+    assert!(controller.verify_at("vc", &signature, issuance_sn)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_dispatcher_update_backers() -> Result<(), Error> {
+    use crate::task::HandleResult;
+    use crate::test_support::fresh_dispatcher;
+    use crossbeam_channel::bounded;
+    use keri::{derivation::basic::Basic, signer::CryptoBox};
+
+    let (dispatcher, _dir) = fresh_dispatcher();
+    let dispatcher = Arc::new(dispatcher);
+    dispatcher.listen()?;
+
+    let backer = IdentifierPrefix::Basic(Basic::Ed25519.derive(CryptoBox::new()?.public_key()));
+    let (sender, receiver) = bounded(0);
+    dispatcher.update_backers(vec![backer.clone()], vec![], sender)?;
+    assert!(matches!(
+        receiver.recv().map(|r| r.result),
+        Ok(HandleResult::BackersUpdated)
+    ));
+
+    let controller = dispatcher.controller.read().unwrap();
+    let registry_state = controller.tel.get_management_tel_state()?;
+    assert_eq!(registry_state.sn, 1);
+    let management_events = controller.tel.get_management_events()?.unwrap();
+    assert!(String::from_utf8(management_events)
+        .unwrap()
+        .contains(&backer.to_str()));
+    // Registry rotation is anchored in KEL.
+    assert_eq!(controller.kerl.get_state()?.unwrap().sn, 2);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_verify_anchoring() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+    use keri::derivation::basic::Basic;
+
+    let (controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc".into()))?;
+
+    let proof = controller.verify_anchoring(&MessageHash::new("vc".as_bytes()).into())?;
+    assert_eq!(proof.kel_event_sn, 2);
+    assert_eq!(
+        proof.signing_keys,
+        vec![Basic::Ed25519.derive(controller.key_manager.public_key())]
+    );
+
+    // Tel events processed without checking source seals, each broken at
+    // different hop.
+    let issuer = controller.issuer_prefix();
+    let vcp_ixn = controller.kerl.get_event_at_sn(&issuer, 1)?.unwrap();
+    let vcp_ixn_digest = SelfAddressing::Blake3_256.derive(&vcp_ixn.serialize()?);
+    let broken = vec![
+        (
+            "no kel event",
+            EventSourceSeal {
+                sn: 10,
+                digest: vcp_ixn_digest.clone(),
+            },
+            AnchoringFailure::MissingKelEvent(10),
+        ),
+        (
+            "wrong digest",
+            EventSourceSeal {
+                sn: 1,
+                digest: SelfAddressing::Blake3_256.derive("other".as_bytes()),
+            },
+            AnchoringFailure::SourceSealMismatch(1),
+        ),
+        (
+            "not anchored",
+            EventSourceSeal {
+                sn: 1,
+                digest: vcp_ixn_digest,
+            },
+            AnchoringFailure::MissingEventSeal(1),
+        ),
+    ];
+    for (message, seal, expected) in broken {
+        let iss = controller.tel.make_issuance_event(message.as_bytes())?;
+        controller.tel.process(iss, seal)?;
+        match controller.verify_anchoring(&MessageHash::new(message.as_bytes()).into()) {
+            Err(Error::Anchoring(failure)) => assert_eq!(failure, expected),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    assert!(matches!(
+        controller.verify_anchoring(&MessageHash::new("unknown".as_bytes()).into()),
+        Err(Error::Anchoring(AnchoringFailure::MissingTelEvent))
+    ));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_message_hash_bytes() -> Result<(), Error> {
+    use rand::Rng;
+
+    let mut rng = rand::thread_rng();
+    for _ in 0..100 {
+        let len = rng.gen_range(0, 256);
+        let payload: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+        let hash = MessageHash::new(&payload);
+
+        let bytes = hash.to_bytes();
+        assert_eq!(bytes.len(), 33);
+        let decoded = MessageHash::from_bytes(&bytes)?;
+        assert_eq!(decoded.to_string(), hash.to_string());
+    }
+    assert!(MessageHash::from_bytes(&[0u8; 5]).is_err());
+
+    Ok(())
+}
+
+#[test]
+pub fn test_message_hash_map() {
+    use std::collections::HashMap;
+
+    assert_eq!(MessageHash::new(b"vc"), MessageHash::new(b"vc"));
+    assert_ne!(MessageHash::new(b"vc"), MessageHash::new(b"other vc"));
+
+    let mut credentials = HashMap::new();
+    for i in 0..10 {
+        let vc = format!("vc{}", i);
+        credentials.insert(MessageHash::new(vc.as_bytes()), vc);
+    }
+    for i in 0..10 {
+        let vc = format!("vc{}", i);
+        assert_eq!(credentials.get(&MessageHash::new(vc.as_bytes())), Some(&vc));
+    }
+    assert!(credentials.get(&MessageHash::new(b"vc10")).is_none());
+}
+
+#[test]
+pub fn test_request_ids() -> Result<(), Error> {
+    use crate::task::HandleResult;
+    use crate::test_support::fresh_dispatcher;
+    use crossbeam_channel::unbounded;
+
+    let (dispatcher, _dir) = fresh_dispatcher();
+    dispatcher.listen()?;
+
+    // All requests share one channel.
+    let (sender, receiver) = unbounded();
+    let issue_id = dispatcher.issue("vc".into(), sender.clone())?;
+    let sign_id = dispatcher.sign(b"msg".to_vec(), sender.clone())?;
+    let kel_id = dispatcher.get_kel(sender)?;
+    assert!(issue_id != sign_id && sign_id != kel_id && issue_id != kel_id);
+
+    for _ in 0..3 {
+        let response = receiver.recv().unwrap();
+        let request_id = response.request_id;
+        match response.result {
+            HandleResult::Issued(_) => assert_eq!(request_id, issue_id),
+            HandleResult::MessageSigned(_) => assert_eq!(request_id, sign_id),
+            HandleResult::GotKel(_) => assert_eq!(request_id, kel_id),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn test_task_failures() -> Result<(), Error> {
+    use crate::{task::HandleResult, test_support::FailingKeyManager};
+    use crossbeam_channel::unbounded;
+    use keri::{derivation::basic::Basic, signer::CryptoBox};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let fail = Arc::new(AtomicBool::new(false));
+    let km = FailingKeyManager::new(Arc::clone(&fail));
+    let dispatcher = Dispatcher::init(km, dir.path())?;
+    dispatcher.listen()?;
+    let (sender, receiver) = unbounded();
+    let is_failure = || {
+        matches!(
+            receiver.recv().map(|r| r.result),
+            Ok(HandleResult::Failure(_))
+        )
+    };
+
+    // Malformed hash.
+    dispatcher.revoke("not a hash".into(), sender.clone())?;
+    assert!(is_failure());
+    // Hash which was never issued.
+    dispatcher.revoke(MessageHash::new(b"vc").to_string(), sender.clone())?;
+    assert!(is_failure());
+
+    fail.store(true, Ordering::SeqCst);
+    dispatcher.issue("vc".into(), sender.clone())?;
+    assert!(is_failure());
+    dispatcher.sign(b"msg".to_vec(), sender.clone())?;
+    assert!(is_failure());
+    let backer = IdentifierPrefix::Basic(Basic::Ed25519.derive(CryptoBox::new()?.public_key()));
+    dispatcher.update_backers(vec![backer], vec![], sender.clone())?;
+    assert!(is_failure());
+
+    // Reading logs doesn't need signing.
+    dispatcher.get_kel(sender.clone())?;
+    assert!(matches!(
+        receiver.recv().map(|r| r.result),
+        Ok(HandleResult::GotKel(_))
+    ));
+    dispatcher.get_tel(MessageHash::new(b"vc"), sender.clone())?;
+    assert!(matches!(
+        receiver.recv().map(|r| r.result),
+        Ok(HandleResult::GotTel(_))
+    ));
+
+    // Task which panicked while holding controller lock poisons it. Reading
+    // tasks fail after that, instead of panicking too.
+    let controller = Arc::clone(&dispatcher.controller);
+    let _ = std::thread::spawn(move || {
+        let _controller = controller.write().unwrap();
+        panic!("Task panicked");
+    })
+    .join();
+    dispatcher.get_kel(sender.clone())?;
+    assert!(is_failure());
+    dispatcher.get_tel(MessageHash::new(b"vc"), sender)?;
+    assert!(is_failure());
+
+    Ok(())
+}
+
+#[test]
+pub fn test_ingest_management() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+    use keri::{derivation::basic::Basic, signer::CryptoBox};
+    use tempfile::tempdir;
+
+    let (controller, _dir) = fresh_controller();
+    let backer = IdentifierPrefix::Basic(Basic::Ed25519.derive(CryptoBox::new()?.public_key()));
+    controller.update_backers(&[backer], &[])?;
+    controller.update(UpdateType::Issue("vc".into()))?;
+
+    let verifier_kel_dir = tempdir().unwrap();
+    let verifier_kerl = KERL::new(verifier_kel_dir.path())?;
+    verifier_kerl.process_kel(&controller.get_kerl()?.unwrap())?;
+    let verifier_tel_dir = tempdir().unwrap();
+    let mut verifier_tel = Tel::new(verifier_tel_dir.path())?;
+
+    // Vc events are not accepted.
+    let mut vc_tel = vec![];
+    controller
+        .tel
+        .write_tel(&MessageHash::new(b"vc").into(), &mut vc_tel)?;
+    assert!(verifier_tel
+        .ingest_management(&vc_tel, &verifier_kerl)
+        .is_err());
+
+    let management_tel = controller.tel.get_management_events()?.unwrap();
+    let state = verifier_tel.ingest_management(&management_tel, &verifier_kerl)?;
+    let expected = controller.tel.get_management_tel_state()?;
+    assert_eq!(state.prefix, controller.registry_prefix()?);
+    assert_eq!(state.sn, 1);
+    assert_eq!(state.last, expected.last);
+    assert_eq!(state.issuer, controller.issuer_prefix());
+    assert_eq!(
+        verifier_tel.get_management_events()?.unwrap(),
+        management_tel
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn test_ingest_sn_chain() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+    use tempfile::tempdir;
+
+    let (controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc".into()))?;
+    controller.update(UpdateType::Revoke(MessageHash::new(b"vc")))?;
+    let message_hash: SelfAddressingPrefix = MessageHash::new(b"vc").into();
+    let vc_events = controller.tel.get_tel(&message_hash)?;
+
+    let verifier_kel_dir = tempdir().unwrap();
+    let verifier_kerl = KERL::new(verifier_kel_dir.path())?;
+    verifier_kerl.process_kel(&controller.get_kerl()?.unwrap())?;
+    let verifier_tel_dir = tempdir().unwrap();
+    let mut verifier_tel = Tel::new(verifier_tel_dir.path())?;
+    let management_tel = controller.tel.get_management_events()?.unwrap();
+    verifier_tel.ingest_management(&management_tel, &verifier_kerl)?;
+
+    // Revocation without issuance, sn 0 is missing.
+    let revocation = vc_events[1].serialize()?;
+    assert!(matches!(
+        verifier_tel.ingest(&revocation, &verifier_kerl),
+        Err(Error::RejectedEvents(_))
+    ));
+    assert!(!verifier_tel.contains(&message_hash)?);
+
+    // Issuance sent twice.
+    let issuance = vc_events[0].serialize()?;
+    verifier_tel.ingest(&issuance, &verifier_kerl)?;
+    assert!(matches!(
+        verifier_tel.ingest(&issuance, &verifier_kerl),
+        Err(Error::RejectedEvents(_))
+    ));
+    assert_eq!(verifier_tel.get_tel(&message_hash)?.len(), 1);
+
+    verifier_tel.ingest(&revocation, &verifier_kerl)?;
+    assert!(matches!(
+        verifier_tel.get_vc_state(&message_hash)?,
+        TelState::Revoked
+    ));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_verify_detailed() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+    use keri::derivation::basic::Basic;
+
+    let (mut controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc1".into()))?;
+    let signature1 = controller.sign(&b"vc1".to_vec())?;
+    let key1 = Basic::Ed25519.derive(controller.key_manager.public_key());
+
+    Arc::get_mut(&mut controller.key_manager)
+        .unwrap()
+        .rotate()?;
+    controller.kerl.rotate(controller.key_manager.as_ref())?;
+    let rotation_sn = controller.kerl.get_state()?.unwrap().sn;
+    controller.update(UpdateType::Issue("vc2".into()))?;
+    let signature2 = controller.sign(&b"vc2".to_vec())?;
+    let key2 = Basic::Ed25519.derive(controller.key_manager.public_key());
+
+    let outcome = controller.verify_detailed(b"vc1", &signature1)?;
+    assert!(outcome.verified);
+    assert_eq!(outcome.matched_keys, vec![key1]);
+    assert_eq!(outcome.establishment_sn, Some(0));
+    assert!(matches!(outcome.credential_state, TelState::Issued(_)));
+
+    let outcome = controller.verify_detailed(b"vc2", &signature2)?;
+    assert!(outcome.verified);
+    assert_eq!(outcome.matched_keys, vec![key2]);
+    assert_eq!(outcome.establishment_sn, Some(rotation_sn));
+
+    // Signature made with keys from after rotation doesn't verify vc1.
+    let outcome = controller.verify_detailed(b"vc1", &controller.sign(&b"vc1".to_vec())?)?;
+    assert!(!outcome.verified);
+    assert!(outcome.matched_keys.is_empty());
+    assert_eq!(outcome.establishment_sn, Some(0));
+
+    let outcome = controller.verify_detailed(b"vc3", &signature2)?;
+    assert!(!outcome.verified);
+    assert_eq!(outcome.establishment_sn, None);
+    assert!(matches!(outcome.credential_state, TelState::NotIsuued));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_current_keys() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+    use keri::{
+        derivation::basic::Basic,
+        event::sections::{key_config::nxt_commit, threshold::SignatureThreshold},
+    };
+
+    let (mut controller, _dir) = fresh_controller();
+    let next_key = Basic::Ed25519.derive(controller.key_manager.next_public_key());
+    assert_eq!(
+        controller.current_keys()?,
+        vec![Basic::Ed25519.derive(controller.key_manager.public_key())]
+    );
+    assert_eq!(
+        controller.next_key_digests()?,
+        vec![nxt_commit(
+            &SignatureThreshold::Simple(1),
+            &[next_key.clone()],
+            &SelfAddressing::Blake3_256
+        )]
+    );
+
+    let keys_before = controller.current_keys()?;
+    Arc::get_mut(&mut controller.key_manager)
+        .unwrap()
+        .rotate()?;
+    controller.kerl.rotate(controller.key_manager.as_ref())?;
+    assert_ne!(controller.current_keys()?, keys_before);
+    // Keys committed before rotation became current.
+    assert_eq!(controller.current_keys()?, vec![next_key]);
+
+    Arc::get_mut(&mut controller.key_manager)
+        .unwrap()
+        .rotate()?;
+    controller.kerl.abandon(controller.key_manager.as_ref())?;
+    assert!(controller.next_key_digests()?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+pub fn test_can_rotate() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (mut controller, _dir) = fresh_controller();
+    assert!(controller.can_rotate()?);
+
+    let km = Arc::get_mut(&mut controller.key_manager).unwrap();
+    km.rotate()?;
+    controller.kerl.rotate(controller.key_manager.as_ref())?;
+    assert!(controller.can_rotate()?);
+
+    let km = Arc::get_mut(&mut controller.key_manager).unwrap();
+    km.rotate()?;
+    controller.kerl.abandon(controller.key_manager.as_ref())?;
+    assert!(!controller.can_rotate()?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_event_kinds() -> Result<(), Error> {
+    use crate::{kerl::EventKind, test_support::fresh_controller};
+
+    let (controller, _dir) = fresh_controller();
+    assert_eq!(
+        controller.kerl.event_kinds()?,
+        vec![EventKind::Icp, EventKind::Ixn]
+    );
+
+    controller.update(UpdateType::Issue("vc".into()))?;
+    assert_eq!(
+        controller.kerl.event_kinds()?,
+        vec![EventKind::Icp, EventKind::Ixn, EventKind::Ixn]
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn test_get_anchoring_event() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc".into()))?;
+    let message_hash: SelfAddressingPrefix = MessageHash::new(b"vc").into();
+
+    let anchoring_event = controller.get_anchoring_event(&message_hash)?;
+    assert_eq!(anchoring_event.event.sn, 2);
+    let issuance = controller.tel.get_tel(&message_hash)?.remove(0).event;
+    match anchoring_event.event.event_data {
+        EventData::Ixn(ixn) => assert!(ixn.data.iter().any(|seal| matches!(
+            seal,
+            Seal::Event(es) if es.prefix == issuance.get_prefix() && es.sn == 0
+        ))),
+        _ => panic!("Expected interaction event"),
+    }
+
+    assert!(matches!(
+        controller.get_anchoring_event(&MessageHash::new(b"unknown").into()),
+        Err(Error::Anchoring(AnchoringFailure::MissingTelEvent))
+    ));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_get_anchor_sn() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("other vc".into()))?;
+    controller.update(UpdateType::Issue("vc".into()))?;
+    let message_hash: SelfAddressingPrefix = MessageHash::new(b"vc").into();
+    let issuance_sn = controller.get_anchoring_event(&message_hash)?.event.sn;
+    assert_eq!(issuance_sn, 3);
+    assert_eq!(controller.tel.get_anchor_sn(&message_hash, 0)?, issuance_sn);
+
+    controller.update(UpdateType::Revoke(MessageHash::new(b"vc")))?;
+    assert_eq!(
+        controller.tel.get_anchor_sn(&message_hash, 1)?,
+        controller.kerl.get_state()?.unwrap().sn
+    );
+    assert!(controller.tel.get_anchor_sn(&message_hash, 2).is_err());
+
+    Ok(())
+}
+
+#[test]
+pub fn test_issuance_proof_with_time() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+    use keri::signer::CryptoBox;
+    use tempfile::tempdir;
+
+    let (controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc".into()))?;
+    let message_hash: SelfAddressingPrefix = MessageHash::new(b"vc").into();
+
+    // Not receipted yet.
+    let proof = controller.issuance_proof_with_time(&message_hash)?;
+    assert_eq!(proof.anchoring_event.event.sn, 2);
+    assert!(proof.witnesses.is_empty());
+    assert!(proof.receipts.is_empty());
+
+    let witness_dir = tempdir().unwrap();
+    let witness_km = CryptoBox::new()?;
+    let mut witness = KERL::new(witness_dir.path())?;
+    witness.incept(&witness_km)?;
+    let receipts = witness.respond(&controller.get_kerl()?.unwrap(), &witness_km)?;
+    controller.kerl.process_kel(&receipts)?;
+
+    let proof = controller.issuance_proof_with_time(&message_hash)?;
+    assert_eq!(proof.anchoring_event.event.sn, 2);
+    assert_eq!(proof.witnesses, vec![witness.get_prefix()]);
+    assert_eq!(proof.receipts.len(), 1);
+    assert_eq!(proof.receipts[0].body.event.sn, 2);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_restore_from_streams() -> Result<(), Error> {
+    use crate::{key_manager::seeded::SeededKeyManager, test_support::fresh_controller};
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let controller = Controller::init(SeededKeyManager::new(), dir.path())?;
+    controller.update(UpdateType::Issue("vc1".into()))?;
+    let vc1: SelfAddressingPrefix = MessageHash::new(b"vc1").into();
+
+    let kel = controller.get_kerl()?.unwrap();
+    let mut tel = controller.tel.get_management_events()?.unwrap();
+    controller.tel.write_tel(&vc1, &mut tel)?;
+    let (current, next) = controller.key_manager.seeds();
+
+    let restored_dir = tempdir().unwrap();
+    let restored = Controller::restore_from_streams(
+        SeededKeyManager::from_seeds(&current, &next)?,
+        restored_dir.path(),
+        &kel,
+        &tel,
+    )?;
+    assert_eq!(restored.issuer_prefix(), controller.issuer_prefix());
+    assert_eq!(restored.registry_prefix()?, controller.registry_prefix()?);
+    assert!(matches!(
+        restored.tel.get_vc_state(&vc1)?,
+        TelState::Issued(_)
+    ));
+
+    restored.update(UpdateType::Issue("vc2".into()))?;
+    let signature = restored.sign(&b"vc2".to_vec())?;
+    assert!(restored.verify("vc2", &signature)?);
+
+    // Tel of other registry isn't anchored in restored KEL.
+    let (other, _other_dir) = fresh_controller();
+    let other_tel = other.tel.get_management_events()?.unwrap();
+    let failed_dir = tempdir().unwrap();
+    assert!(Controller::restore_from_streams(
+        SeededKeyManager::from_seeds(&current, &next)?,
+        failed_dir.path(),
+        &kel,
+        &other_tel,
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+pub fn test_issue_multisig() -> Result<(), Error> {
+    use crate::{kerl::verify_signatures, test_support::fresh_multisig_controller};
+
+    // Identifier with 2 of 2 signing threshold.
+    let (controller, km2, _dir) = fresh_multisig_controller(2);
+    let km1 = controller.key_manager.as_ref();
+
+    // Single key can't anchor issuance.
+    assert!(controller.update(UpdateType::Issue("vc0".into())).is_err());
+
+    let signatures = controller.issue_multisig(b"vc1", &[km1, &km2])?;
+    let indexes: Vec<_> = signatures.iter().map(|sig| sig.index).collect();
+    assert_eq!(indexes, vec![0, 1]);
+    let state = controller.kerl.get_state()?.unwrap();
+    assert!(verify_signatures(&state, b"vc1", &signatures)?);
+    assert!(!verify_signatures(&state, b"vc1", &signatures[..1])?);
+
+    // Indexes follow key list, not order of signers.
+    let signatures = controller.issue_multisig(b"vc2", &[&km2, km1])?;
+    let indexes: Vec<_> = signatures.iter().map(|sig| sig.index).collect();
+    assert_eq!(indexes, vec![1, 0]);
+    let state = controller.kerl.get_state()?.unwrap();
+    assert!(verify_signatures(&state, b"vc2", &signatures)?);
+    assert!(matches!(
+        controller
+            .tel
+            .get_vc_state(&MessageHash::new(b"vc2").into())?,
+        TelState::Issued(_)
+    ));
+    let metrics = controller.metrics();
+    assert_eq!(metrics.issued, 2);
+    assert_eq!(metrics.failed, 1);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_sign_at_index() -> Result<(), Error> {
+    use crate::{kerl::verify_signatures, test_support::fresh_multisig_controller};
+
+    // Identifier with 2 of 2 signing threshold.
+    let (member1, km2, dir) = fresh_multisig_controller(2);
+    // Every member holds its own key manager.
+    let kerl = Arc::clone(&member1.kerl);
+    let member2 =
+        Controller::from_parts(km2, Arc::clone(&kerl), Arc::clone(&member1.tel), dir.path());
+
+    let message = b"multisig message";
+    let signatures = vec![
+        member1.sign_at_index(message, 0)?,
+        member2.sign_at_index(message, 1)?,
+    ];
+    assert_eq!(signatures[1].index, 1);
+    let state = kerl.get_state()?.unwrap();
+    assert!(verify_signatures(&state, message, &signatures)?);
+    assert!(!verify_signatures(&state, message, &signatures[..1])?);
+
+    // Index of other member's key.
+    assert!(member1.sign_at_index(message, 1).is_err());
+    // Index out of range of current keys.
+    assert!(member2.sign_at_index(message, 2).is_err());
+
+    Ok(())
+}
+
+#[test]
+pub fn test_sign_not_current() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (mut controller, _dir) = fresh_controller();
+    Arc::get_mut(&mut controller.key_manager)
+        .unwrap()
+        .rotate()?;
+    // Key manager is rotated, but KEL isn't.
+    assert!(controller.sign(&b"vc".to_vec()).is_err());
+    assert!(controller.update(UpdateType::Issue("vc".into())).is_err());
+
+    controller.kerl.rotate(controller.key_manager.as_ref())?;
+    controller.update(UpdateType::Issue("vc".into()))?;
+    let signature = controller.sign(&b"vc".to_vec())?;
+    assert!(controller.verify("vc", &signature)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_verify_key_at_other_index() -> Result<(), Error> {
+    use crate::test_support::fresh_multisig_controller;
+    use keri::derivation::{basic::Basic, self_signing::SelfSigning};
+
+    // Identifier with 1 of 2 signing threshold, which signs with second key.
+    let (first, km2, dir) = fresh_multisig_controller(1);
+    let controller = Controller::from_parts(
+        km2,
+        Arc::clone(&first.kerl),
+        Arc::clone(&first.tel),
+        dir.path(),
+    );
+    let km1 = first.key_manager.as_ref();
+
+    controller.issue_multisig(b"vc", &[controller.key_manager.as_ref()])?;
+    let signature = controller.sign(&b"vc".to_vec())?;
+    assert_eq!(signature.index, 1);
+    assert!(controller.verify("vc", &signature)?);
+    let outcome = controller.verify_detailed(b"vc", &signature)?;
+    assert_eq!(
+        outcome.matched_keys,
+        vec![Basic::Ed25519.derive(controller.key_manager.public_key())]
+    );
+    // Signature of the first key indexed as made by the second one.
+    let misindexed = Signature::new(SelfSigning::Ed25519Sha512, 1, km1.sign(&b"vc".to_vec())?);
+    assert!(!controller.verify("vc", &misindexed)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_subscribe() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+    use keri::{derivation::basic::Basic, signer::CryptoBox};
+
+    let (controller, _dir) = fresh_controller();
+    // Events before subscribing are not received.
+    controller.update(UpdateType::Issue("vc0".into()))?;
+
+    let receiver = controller.subscribe();
+    controller.update(UpdateType::Issue("vc1".into()))?;
+    let hash = MessageHash::new(b"vc1");
+    assert_eq!(
+        receiver.try_recv().unwrap(),
+        RegistryEvent::Issued(hash.clone().into())
+    );
+
+    let late_receiver = controller.subscribe();
+    controller.update(UpdateType::Revoke(hash.clone()))?;
+    let backer = IdentifierPrefix::Basic(Basic::Ed25519.derive(CryptoBox::new()?.public_key()));
+    controller.update_backers(&[backer], &[])?;
+    for receiver in &[&receiver, &late_receiver] {
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            RegistryEvent::Revoked(hash.clone().into())
+        );
+        assert_eq!(receiver.try_recv().unwrap(), RegistryEvent::Rotated(1));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    // Dropped subscriber doesn't break notifications.
+    drop(late_receiver);
+    controller.update(UpdateType::Issue("vc2".into()))?;
+    assert!(matches!(receiver.try_recv(), Ok(RegistryEvent::Issued(_))));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_preview_issue() -> Result<(), Error> {
+    use crate::test_support::FailingKeyManager;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let fail = Arc::new(AtomicBool::new(false));
+    let controller = Controller::init(FailingKeyManager::new(Arc::clone(&fail)), dir.path())?;
+    let kel_before = controller.get_kerl()?;
+    let state_before = controller.kerl.get_state()?.unwrap();
+
+    // Preview doesn't sign, so it works without signer.
+    fail.store(true, Ordering::SeqCst);
+    let preview = controller.preview_issue("vc")?;
+    fail.store(false, Ordering::SeqCst);
+    let hash: SelfAddressingPrefix = MessageHash::new(b"vc").into();
+    assert_eq!(
+        preview.tel_event.get_prefix(),
+        IdentifierPrefix::SelfAddressing(hash.clone())
+    );
+    assert_eq!(preview.tel_event.get_sn(), 0);
+    assert!(matches!(
+        &preview.event_seal,
+        Seal::Event(seal) if seal.prefix == preview.tel_event.get_prefix()
+    ));
+    let ixn = &preview.anchoring_event;
+    assert_eq!(ixn.event.sn, state_before.sn + 1);
+    assert_eq!(preview.source_seal.sn, ixn.event.sn);
+
+    // Nothing was processed.
+    assert_eq!(controller.get_kerl()?, kel_before);
+    assert!(!controller.tel.contains(&hash)?);
+    assert!(controller.tel.list_issued()?.is_empty());
+
+    // Issuance makes the same anchoring event.
+    controller.update(UpdateType::Issue("vc".into()))?;
+    assert_eq!(
+        controller.get_anchoring_event(&hash)?.serialize()?,
+        ixn.serialize()?
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn test_vc_status_json() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    let hash = MessageHash::new(b"vc");
+    let status = |controller: &Controller<_>| -> Result<serde_json::Value, Error> {
+        let json = controller.get_vc_status_json(&hash.clone().into())?;
+        serde_json::from_str(&json).map_err(|e| Error::Parse(e.to_string()))
+    };
+
+    assert_eq!(
+        status(&controller)?,
+        serde_json::json!({ "state": "not_issued" })
+    );
+    let issued_at = "2021-07-01T12:00:00Z";
+    controller.update_at(UpdateType::Issue("vc".into()), Some(issued_at))?;
+    assert_eq!(
+        status(&controller)?,
+        serde_json::json!({ "state": "issued", "sn": 0, "local_issued_at": issued_at })
+    );
+    controller.revoke(hash.clone(), RevocationReason::Superseded)?;
+    assert_eq!(
+        status(&controller)?,
+        serde_json::json!({
+            "state": "revoked",
+            "sn": 1,
+            "local_issued_at": issued_at,
+            "revocation_reason": "superseded"
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn test_issue_after_revocation() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    let message = "vc";
+    controller.update(UpdateType::Issue(message.into()))?;
+    let signature = controller.sign(&message.as_bytes().to_vec())?;
+    controller.update(UpdateType::Revoke(MessageHash::new(message.as_bytes())))?;
+
+    // Revoked vc stays revoked, and nothing is anchored for it.
+    let kel_sn = controller.kerl.get_state()?.unwrap().sn;
+    assert!(matches!(
+        controller.update(UpdateType::Issue(message.into())),
+        Err(Error::AlreadyIssued {
+            state: VcStatusKind::Revoked
+        })
+    ));
+    assert_eq!(controller.kerl.get_state()?.unwrap().sn, kel_sn);
+    assert!(!controller.verify(message, &signature)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_flush() -> Result<(), Error> {
+    use crate::controller::builder::ControllerBuilder;
+    use keri::signer::CryptoBox;
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let (hash, signature): (SelfAddressingPrefix, _) = {
+        let controller = ControllerBuilder::new()
+            .durable(true)
+            .build(CryptoBox::new()?, dir.path())?;
+        controller.update_at(UpdateType::Issue("vc".into()), Some("2021-07-01T12:00:00Z"))?;
+        controller.create_registry("diplomas")?;
+        controller.flush()?;
+        (
+            MessageHash::new(b"vc").into(),
+            controller.sign(&b"vc".to_vec())?,
+        )
+    };
+
+    let controller = Controller::load(CryptoBox::new()?, dir.path())?;
+    assert!(controller.verify("vc", &signature)?);
+    assert_eq!(controller.tel.list_issued()?, vec![hash.clone()]);
+    assert_eq!(
+        controller.tel.get_vc_status(&hash)?.local_issued_at,
+        Some("2021-07-01T12:00:00Z".to_string())
+    );
+    assert!(controller.registry("diplomas").is_ok());
+
+    Ok(())
+}
+
+#[test]
+pub fn test_update_datetime() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    let hash: SelfAddressingPrefix = MessageHash::new(b"vc").into();
+
+    // Invalid datetime is rejected before anything is anchored.
+    let kel_sn = controller.kerl.get_state()?.unwrap().sn;
+    assert!(matches!(
+        controller.update_at(UpdateType::Issue("vc".into()), Some("1 July 2021")),
+        Err(Error::Parse(_))
+    ));
+    assert_eq!(controller.kerl.get_state()?.unwrap().sn, kel_sn);
+
+    controller.update_at(
+        UpdateType::Issue("vc".into()),
+        Some("2021-07-01T12:00:00.5+02:00"),
+    )?;
+    assert_eq!(
+        controller.tel.get_event_datetime(&hash, 0)?,
+        Some("2021-07-01T12:00:00.5+02:00".to_string())
+    );
+
+    // Without datetime, current time is recorded.
+    controller.update(UpdateType::Revoke(MessageHash::new(b"vc")))?;
+    let revoked_at = controller.tel.get_event_datetime(&hash, 1)?.unwrap();
+    assert!(validate_datetime(&revoked_at).is_ok());
+    assert!(revoked_at.as_str() > "2021");
+
+    Ok(())
+}
+
+#[cfg(feature = "secp256k1")]
+#[test]
+pub fn test_verify_secp256k1() -> Result<(), Error> {
+    use crate::test_support::Secp256k1KeyManager;
+    use keri::derivation::self_signing::SelfSigning;
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let mut controller = Controller::init(Secp256k1KeyManager::new(), dir.path())?;
+    controller.update(UpdateType::Issue("vc1".into()))?;
+    let signature1 = controller.sign(&b"vc1".to_vec())?;
+    assert_eq!(signature1.code, SelfSigning::ECDSAsecp256k1Sha256);
+    assert!(controller.verify("vc1", &signature1)?);
+    assert!(!controller.verify("vc1", &controller.sign(&b"other".to_vec())?)?);
+
+    // Rotation and anchoring after it are signed with secp256k1 keys too.
+    Arc::get_mut(&mut controller.key_manager)
+        .unwrap()
+        .rotate()?;
+    controller.kerl.rotate(controller.key_manager.as_ref())?;
+    controller.update(UpdateType::Issue("vc2".into()))?;
+    let signature2 = controller.sign(&b"vc2".to_vec())?;
+    assert!(controller.verify("vc2", &signature2)?);
+    assert!(controller.verify("vc1", &signature1)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_verify_after_external_rotation() -> Result<(), Error> {
+    use crate::{kerl::event_generator, test_support::fresh_controller};
+    use keri::derivation::{basic::Basic, self_signing::SelfSigning};
+
+    let (mut controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc1".into()))?;
+    let signature1 = controller.sign(&b"vc1".to_vec())?;
+    let key1 = Basic::Ed25519.derive(controller.key_manager.public_key());
+    // Fill cached state before rotation.
+    let state = controller.kerl.get_state()?.unwrap();
+
+    // Rotation made outside of controller and processed as external KEL.
+    let km = Arc::get_mut(&mut controller.key_manager).unwrap();
+    km.rotate()?;
+    let rot = event_generator::make_rot(&*km, state)?;
+    let rot = rot.sign(vec![AttachedSignaturePrefix::new(
+        SelfSigning::Ed25519Sha512,
+        km.sign(&rot.serialize()?)?,
+        0,
+    )]);
+    controller.kerl.process_kel(&rot.serialize()?)?;
+    let key2 = Basic::Ed25519.derive(controller.key_manager.public_key());
+    assert_eq!(
+        controller.kerl.get_state()?.unwrap().current.public_keys,
+        vec![key2.clone()]
+    );
+
+    controller.update(UpdateType::Issue("vc2".into()))?;
+    let signature2 = controller.sign(&b"vc2".to_vec())?;
+
+    // Pre-rotation vc resolves to historical keys.
+    assert_eq!(
+        controller.get_pub_key(&MessageHash::new(b"vc1").into())?,
+        vec![key1]
+    );
+    assert!(controller.verify("vc1", &signature1)?);
+    assert!(!controller.verify("vc1", &controller.sign(&b"vc1".to_vec())?)?);
+
+    // Post-rotation vc resolves to new keys.
+    assert_eq!(
+        controller.get_pub_key(&MessageHash::new(b"vc2").into())?,
+        vec![key2]
+    );
+    assert!(controller.verify("vc2", &signature2)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_verify_at() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    let before_issuance = controller.kerl.get_state()?.unwrap().sn;
+    controller.update(UpdateType::Issue("vc".into()))?;
+    let issuance_sn = controller.kerl.get_state()?.unwrap().sn;
+    let signature = controller.sign(&b"vc".to_vec())?;
+    controller.update(UpdateType::Revoke(MessageHash::new(b"vc")))?;
+    let revocation_sn = controller.kerl.get_state()?.unwrap().sn;
+
+    assert!(!controller.verify("vc", &signature)?);
+    assert!(controller.verify_at("vc", &signature, issuance_sn)?);
+    assert!(!controller.verify_at("vc", &signature, revocation_sn)?);
+    assert!(!controller.verify_at("vc", &signature, before_issuance)?);
+    assert!(!controller.verify_at("vc", &controller.sign(&b"other".to_vec())?, issuance_sn)?);
+    assert!(controller
+        .verify_at("vc", &signature, revocation_sn + 1)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+pub fn test_canonical_bytes() -> Result<(), Error> {
+    use crate::{tel::parse_tel_stream, test_support::fresh_controller};
+
+    let (controller, _dir) = fresh_controller();
+    let iss = controller.tel.make_issuance_event(b"vc")?;
+    let seal = match to_event_seal(&iss)? {
+        Seal::Event(seal) => seal,
+        _ => unreachable!(),
+    };
+    controller.update(UpdateType::Issue("vc".into()))?;
+
+    // Event read back from tel has the same bytes as the one sealed before
+    // processing.
+    let stored = parse_tel_stream(&controller.get_tel(MessageHash::new(b"vc"))?[0])?;
+    let stored = &stored[0];
+    assert_eq!(canonical_bytes(&stored.event)?, canonical_bytes(&iss)?);
+    assert!(seal
+        .event_digest
+        .verify_binding(&canonical_bytes(&stored.event)?));
+    assert!(controller.kerl.check_seal(
+        stored.seal.seal.sn,
+        &controller.issuer_prefix(),
+        &stored.event
+    )?);
+    assert!(matches!(
+        controller.get_anchoring_event(&MessageHash::new(b"vc").into())?.event.event_data,
+        EventData::Ixn(ixn) if ixn.data.iter().any(|s| matches!(
+            s,
+            Seal::Event(es) if es.event_digest == seal.event_digest
+        ))
+    ));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_reanchor_live() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+    use keri::derivation::basic::Basic;
+
+    let (mut controller, _dir) = fresh_controller();
+    for vc in &["vc1", "vc2"] {
+        controller.update(UpdateType::Issue(vc.to_string()))?;
+    }
+    let issuance_sn = controller.kerl.get_state()?.unwrap().sn;
+    controller.update(UpdateType::Revoke(MessageHash::new(b"vc2")))?;
+    let old_signature = controller.sign(&b"vc1".to_vec())?;
+
+    Arc::get_mut(&mut controller.key_manager)
+        .unwrap()
+        .rotate()?;
+    controller.kerl.rotate(controller.key_manager.as_ref())?;
+    let rotation_sn = controller.kerl.get_state()?.unwrap().sn;
+    let new_key = Basic::Ed25519.derive(controller.key_manager.public_key());
+
+    // Revoked vc2 is skipped.
+    assert_eq!(controller.reanchor_live()?, 1);
+    assert!(controller
+        .tel
+        .get_reanchored(&MessageHash::new(b"vc2").into())?
+        .is_none());
+
+    let outcome = controller.verify_detailed(b"vc1", &controller.sign(&b"vc1".to_vec())?)?;
+    assert!(outcome.verified);
+    assert_eq!(outcome.matched_keys, vec![new_key.clone()]);
+    assert_eq!(outcome.establishment_sn, Some(rotation_sn));
+    assert_eq!(
+        controller.get_pub_key(&MessageHash::new(b"vc1").into())?,
+        vec![new_key]
+    );
+    assert!(!controller.verify_bytes(b"vc1", &old_signature)?);
+    // Point in time verification uses keys from the moment of issuance.
+    assert!(controller.verify_at("vc1", &old_signature, issuance_sn)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_get_tel_events() -> Result<(), Error> {
+    use crate::{
+        tel::{is_revocation, parse_tel_stream},
+        test_support::fresh_controller,
+    };
+
+    let (controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc".into()))?;
+    let message_hash = MessageHash::new(b"vc");
+    controller.update(UpdateType::Revoke(message_hash.clone()))?;
+
+    let tel = controller.get_tel(message_hash.clone())?;
+    assert_eq!(tel.len(), 2);
+    let events = tel
+        .iter()
+        .map(|event| {
+            let mut parsed = parse_tel_stream(event)?;
+            assert_eq!(parsed.len(), 1);
+            Ok(parsed.remove(0))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    assert_eq!(events[0].event.get_sn(), 0);
+    assert_eq!(events[1].event.get_sn(), 1);
+    assert!(is_revocation(&events[1].event));
+    let stored = controller.tel.get_tel(&message_hash.into())?;
+    for (event, stored) in events.iter().zip(&stored) {
+        assert_eq!(event.serialize()?, stored.serialize()?);
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn test_dispatcher_derivation() -> Result<(), Error> {
+    use crate::{task::HandleResult, test_support::fresh_dispatcher};
+    use crossbeam_channel::bounded;
+
+    let (dispatcher, _dir) = fresh_dispatcher();
+    let dispatcher = dispatcher.with_derivation(SelfAddressing::SHA3_256);
+    dispatcher.listen()?;
+
+    let (sender, receiver) = bounded(1);
+    dispatcher.issue("vc".into(), sender.clone())?;
+    let signature = match receiver.recv().unwrap().result {
+        HandleResult::Issued(signature) => signature,
+        result => panic!("Unexpected result: {:?}", result),
+    };
+
+    // Vc is stored under hash of configured derivation only.
+    let hash = MessageHash::new_with(b"vc", SelfAddressing::SHA3_256);
+    assert_ne!(hash, MessageHash::new(b"vc"));
+    dispatcher.get_tel(hash.clone(), sender.clone())?;
+    assert!(matches!(
+        receiver.recv().unwrap().result,
+        HandleResult::GotTel(tel) if tel.len() == 1
+    ));
+    dispatcher.get_tel(MessageHash::new(b"vc"), sender.clone())?;
+    assert!(matches!(
+        receiver.recv().unwrap().result,
+        HandleResult::GotTel(tel) if tel.is_empty()
+    ));
+
+    // Lookups use the same derivation.
+    let controller = dispatcher.controller.read().unwrap();
+    assert!(controller.verify("vc", &signature)?);
+    drop(controller);
+
+    dispatcher.revoke(hash.to_string(), sender)?;
+    assert!(matches!(
+        receiver.recv().unwrap().result,
+        HandleResult::Revoked
+    ));
+    assert!(!dispatcher
+        .controller
+        .read()
+        .unwrap()
+        .verify("vc", &signature)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_repair() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    let receiver = controller.subscribe();
+    let hash = MessageHash::new(b"vc");
+    let iss = controller.tel.make_issuance_event(b"vc")?;
+    let anchoring_sn = controller.kerl.get_state()?.unwrap().sn + 1;
+
+    // Tel write fails after anchoring.
+    let result =
+        controller.anchor_and_process(iss, |_, _| Err(Error::Generic("Tel write failed".into())));
+    assert!(matches!(
+        result,
+        Err(Error::Inconsistent { kel_sn }) if kel_sn == anchoring_sn
+    ));
+    assert_eq!(controller.kerl.get_state()?.unwrap().sn, anchoring_sn);
+    assert!(!controller.tel.contains(&hash.clone().into())?);
+    assert!(receiver.try_recv().is_err());
+
+    assert_eq!(controller.repair()?, 1);
+    assert!(matches!(
+        controller.tel.get_vc_state(&hash.clone().into())?,
+        TelState::Issued(_)
+    ));
+    assert!(controller.verify("vc", &controller.sign(&b"vc".to_vec())?)?);
+    assert_eq!(
+        receiver.try_recv().unwrap(),
+        RegistryEvent::Issued(hash.into())
+    );
+    assert_eq!(controller.repair()?, 0);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_registries() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+    use keri::signer::CryptoBox;
+
+    let (controller, dir) = fresh_controller();
+    let diplomas = controller.create_registry("diplomas")?;
+    let licenses = controller.create_registry("licenses")?;
+    assert!(controller.create_registry("diplomas").is_err());
+    assert!(controller.create_registry("../escape").is_err());
+    assert!(controller
+        .update_in("unknown", UpdateType::Issue("vc".into()))
+        .is_err());
+
+    let registry_prefixes = [
+        controller.registry_prefix()?,
+        diplomas.get_management_tel_state()?.prefix,
+        licenses.get_management_tel_state()?.prefix,
+    ];
+    assert_ne!(registry_prefixes[0], registry_prefixes[1]);
+    assert_ne!(registry_prefixes[1], registry_prefixes[2]);
+    assert_eq!(diplomas.get_issuer()?, controller.issuer_prefix());
+    assert_eq!(licenses.get_issuer()?, controller.issuer_prefix());
+
+    controller.update_in("diplomas", UpdateType::Issue("diploma".into()))?;
+    controller.update_in("licenses", UpdateType::Issue("license".into()))?;
+    // The same message issued in both registries.
+    controller.update_in("diplomas", UpdateType::Issue("shared".into()))?;
+    controller.update_in("licenses", UpdateType::Issue("shared".into()))?;
+    controller.update_in("licenses", UpdateType::Revoke(MessageHash::new(b"shared")))?;
+
+    let hash = |vc: &str| -> SelfAddressingPrefix { MessageHash::new(vc.as_bytes()).into() };
+    assert!(diplomas.contains(&hash("diploma"))?);
+    assert!(!diplomas.contains(&hash("license"))?);
+    assert!(licenses.contains(&hash("license"))?);
+    assert!(!licenses.contains(&hash("diploma"))?);
+    assert!(!controller.tel.contains(&hash("diploma"))?);
+    assert!(matches!(
+        diplomas.get_vc_state(&hash("shared"))?,
+        TelState::Issued(_)
+    ));
+    assert!(matches!(
+        licenses.get_vc_state(&hash("shared"))?,
+        TelState::Revoked
+    ));
+
+    // Registries are opened again on load.
+    drop((diplomas, licenses, controller));
+    let controller = Controller::load(CryptoBox::new()?, dir.path())?;
+    let diplomas = controller.registry("diplomas")?;
+    assert_eq!(
+        diplomas.get_management_tel_state()?.prefix,
+        registry_prefixes[1]
+    );
+    assert!(diplomas.contains(&hash("diploma"))?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_verification_cache() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc".into()))?;
+    let signature = controller.sign(&b"vc".to_vec())?;
+    assert!(controller.verify("vc", &signature)?);
+    assert!(controller.verify("vc", &signature)?);
+    assert_eq!(controller.verification_cache.len(), 1);
+
+    // Revoked vc doesn't verify, even though its state was cached.
+    controller.update(UpdateType::Revoke(MessageHash::new(b"vc")))?;
+    assert!(controller.verification_cache.is_empty());
+    assert!(!controller.verify("vc", &signature)?);
+
+    // Least recently used entry is dropped.
+    let mut controller = controller.with_verification_cache(2);
+    let mut signatures = vec![];
+    for vc in &["vc1", "vc2", "vc3"] {
+        controller.update(UpdateType::Issue(vc.to_string()))?;
+        signatures.push(controller.sign(&vc.as_bytes().to_vec())?);
+    }
+    let issuer_sn = controller.kerl.get_state()?.unwrap().sn;
+    for (vc, signature) in ["vc1", "vc2", "vc1", "vc3"].iter().zip(&[0, 1, 0, 2]) {
+        assert!(controller.verify(vc, &signatures[*signature])?);
+    }
+    assert_eq!(controller.verification_cache.len(), 2);
+    let cached = |vc: &str| {
+        controller
+            .verification_cache
+            .get(&MessageHash::new(vc.as_bytes()).into(), issuer_sn)
+            .is_some()
+    };
+    assert!(cached("vc1"));
+    assert!(!cached("vc2"));
+    assert!(cached("vc3"));
+
+    // Rotation changes sn of issuer's KEL, so cached states are not used.
+    Arc::get_mut(&mut controller.key_manager)
+        .unwrap()
+        .rotate()?;
+    controller.kerl.rotate(controller.key_manager.as_ref())?;
+    assert!(controller.verify("vc1", &signatures[0])?);
+    assert!(!controller.verify("vc1", &controller.sign(&b"vc1".to_vec())?)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_database_locked() -> Result<(), Error> {
+    use keri::signer::CryptoBox;
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let _controller = Controller::init(CryptoBox::new()?, dir.path())?;
+    let (kel_db_path, _) = resolve_db_paths(dir.path());
+
+    // Database is locked by the first controller.
+    match Controller::load(CryptoBox::new()?, dir.path()) {
+        Err(Error::Database { path, .. }) => assert_eq!(path, kel_db_path),
+        _ => panic!("Expected database error"),
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn test_with_kerl() -> Result<(), Error> {
+    use keri::signer::CryptoBox;
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let empty = KERL::new(&dir.path().join("empty"))?;
+    assert!(
+        Controller::with_kerl(empty, &dir.path().join("empty_tel"), CryptoBox::new()?).is_err()
+    );
+
+    // KEL with some events made before registry was attached.
+    let km = CryptoBox::new()?;
+    let mut kerl = KERL::new(&dir.path().join("kel"))?;
+    kerl.incept(&km)?;
+    kerl.make_ixn(Some("payload"), &km)?;
+    let prefix = kerl.get_prefix();
+    let sn = kerl.get_state()?.unwrap().sn;
+
+    let controller = Controller::with_kerl(kerl, &dir.path().join("tel"), km)?;
+    assert_eq!(controller.issuer_prefix(), prefix);
+    assert_eq!(controller.tel.get_issuer()?, prefix);
+    // Only registry inception was anchored, KEL wasn't incepted again.
+    assert_eq!(controller.kerl.get_state()?.unwrap().sn, sn + 1);
+
+    controller.update(UpdateType::Issue("vc".into()))?;
+    assert!(controller.verify("vc", &controller.sign(&b"vc".to_vec())?)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_check_seal_derivation() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    let iss = controller.tel.make_issuance_event(b"vc")?;
+    let sha3_seal = |data: &[u8]| {
+        Seal::Event(EventSeal {
+            prefix: iss.get_prefix(),
+            sn: iss.get_sn(),
+            event_digest: SelfAddressing::SHA3_256.derive(data),
+        })
+    };
+
+    let ixn = controller.kerl.make_ixn_with_seal(
+        &[sha3_seal(&canonical_bytes(&iss)?)],
+        controller.key_manager.as_ref(),
+    )?;
+    assert!(controller.kerl.check_seal(
+        ixn.event_message.event.sn,
+        &controller.issuer_prefix(),
+        &iss
+    )?);
+
+    // Seal of other bytes doesn't match.
+    let ixn = controller.kerl.make_ixn_with_seal(
+        &[sha3_seal(b"other event")],
+        controller.key_manager.as_ref(),
+    )?;
+    assert!(!controller.kerl.check_seal(
+        ixn.event_message.event.sn,
+        &controller.issuer_prefix(),
+        &iss
+    )?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_witnessed_issuer() -> Result<(), Error> {
+    use crate::controller::builder::ControllerBuilder;
+    use keri::signer::CryptoBox;
+    use tempfile::tempdir;
+
+    let mut witnesses = vec![];
+    let mut witness_kms = vec![];
+    let mut witness_dirs = vec![];
+    for _ in 0..2 {
+        let dir = tempdir().unwrap();
+        let km = CryptoBox::new()?;
+        let mut witness = KERL::new(dir.path())?;
+        witness.incept(&km)?;
+        witnesses.push(witness);
+        witness_kms.push(km);
+        witness_dirs.push(dir);
+    }
+    let witness_prefixes = witnesses
+        .iter()
+        .map(|witness| match witness.get_prefix() {
+            IdentifierPrefix::Basic(basic) => basic,
+            _ => unreachable!(),
+        })
+        .collect();
+
+    let dir = tempdir().unwrap();
+    let controller = ControllerBuilder::new()
+        .witnesses(witness_prefixes, 2)
+        .build(CryptoBox::new()?, dir.path())?;
+    let message = "vc";
+    controller.update(UpdateType::Issue(message.into()))?;
+    let signature = controller.sign(&message.as_bytes().to_vec())?;
+
+    assert!(matches!(
+        controller.verify(message, &signature),
+        Err(Error::InsufficientReceipts {
+            required: 2,
+            received: 0
+        })
+    ));
+
+    // Receipt of validator which isn't a witness doesn't count.
+    let other_dir = tempdir().unwrap();
+    let other_km = CryptoBox::new()?;
+    let mut other = KERL::new(other_dir.path())?;
+    other.incept(&other_km)?;
+    let receipts = other.respond(&controller.get_kerl()?.unwrap(), &other_km)?;
+    controller.kerl.process_kel(&receipts)?;
+
+    let receipts = witnesses[0].respond(&controller.get_kerl()?.unwrap(), &witness_kms[0])?;
+    controller.kerl.process_kel(&receipts)?;
+    assert!(matches!(
+        controller.verify(message, &signature),
+        Err(Error::InsufficientReceipts {
+            required: 2,
+            received: 1
+        })
+    ));
+
+    let receipts = witnesses[1].respond(&controller.get_kerl()?.unwrap(), &witness_kms[1])?;
+    controller.kerl.process_kel(&receipts)?;
+    assert!(controller.verify(message, &signature)?);
+    let other_signature = controller.sign(&b"other".to_vec())?;
+    assert!(!controller.verify(message, &other_signature)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_metrics() -> Result<(), Error> {
+    use crate::{task::HandleResult, test_support::fresh_dispatcher};
+    use crossbeam_channel::unbounded;
+
+    let (dispatcher, _dir) = fresh_dispatcher();
+    let (sender, receiver) = unbounded();
+    for vc in &["vc1", "vc2", "vc3"] {
+        dispatcher.issue(vc.to_string(), sender.clone())?;
+    }
+    // Tasks wait in queue until dispatcher listens.
+    let metrics = dispatcher.metrics();
+    assert_eq!(metrics.queue_depth, 3);
+    assert_eq!(metrics.issued, 0);
+
+    dispatcher.listen()?;
+    for _ in 0..3 {
+        assert!(matches!(
+            receiver.recv().map(|r| r.result),
+            Ok(HandleResult::Issued(_))
+        ));
+    }
+    let metrics = dispatcher.metrics();
+    assert_eq!(metrics.queue_depth, 0);
+    assert_eq!(metrics.dequeued, 3);
+    assert_eq!(metrics.issued, 3);
+
+    let controller = dispatcher.controller.read().unwrap();
+    let signature = controller.sign(&b"vc1".to_vec())?;
+    assert!(controller.verify("vc1", &signature)?);
+    assert!(!controller.verify("vc1", &controller.sign(&b"vc2".to_vec())?)?);
+    assert!(controller
+        .update(UpdateType::Revoke(MessageHash::new(b"unknown vc")))
+        .is_err());
+    controller.update(UpdateType::Revoke(MessageHash::new(b"vc1")))?;
+    let metrics = controller.metrics();
+    assert_eq!(metrics.issued, 3);
+    assert_eq!(metrics.revoked, 1);
+    assert_eq!(metrics.verified, 1);
+    assert_eq!(metrics.failed, 2);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_open_or_init() -> Result<(), Error> {
+    use keri::signer::CryptoBox;
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let prefix = {
+        let (controller, created) = Controller::open_or_init(CryptoBox::new()?, dir.path())?;
+        assert!(created);
+        controller.update(UpdateType::Issue("vc".into()))?;
+        controller.kerl.get_prefix()
+    };
+
+    // Existing databases are opened, not incepted again.
+    let (controller, created) = Controller::open_or_init(CryptoBox::new()?, dir.path())?;
+    assert!(!created);
+    assert_eq!(controller.kerl.get_prefix(), prefix);
+    assert!(matches!(
+        controller
+            .tel
+            .get_vc_state(&MessageHash::new(b"vc").into())?,
+        TelState::Issued(_)
+    ));
+    drop(controller);
+
+    let (_controller, created) = Controller::open_or_init(CryptoBox::new()?, dir.path())?;
+    assert!(!created);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_log_orphaned_event() -> Result<(), Error> {
+    use crate::test_support::{capture_logs, fresh_controller};
+    use log::Level;
+
+    let logs = capture_logs();
+    let (controller, _dir) = fresh_controller();
+    let iss = controller.tel.make_issuance_event(b"vc")?;
+    let anchoring_sn = controller.kerl.get_state()?.unwrap().sn + 1;
+
+    // Tel event is kept for repair after failed write.
+    assert!(controller
+        .anchor_and_process(iss, |_, _| Err(Error::Generic("Tel write failed".into())))
+        .is_err());
+    assert!(logs.contains(
+        Level::Warn,
+        &format!(
+            "Tel event anchored at KEL sn {} failed to be processed, kept for repair",
+            anchoring_sn
+        )
+    ));
+    assert!(logs.contains(Level::Trace, "Processed own KEL event"));
+
+    // Failed update is logged as error.
+    assert!(controller
+        .update(UpdateType::Revoke(MessageHash::new(b"unknown vc")))
+        .is_err());
+    assert!(logs.contains(Level::Error, "Update of tel failed"));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_verify_said_signature() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc".into()))?;
+    let said: SelfAddressingPrefix = MessageHash::new(b"vc").into();
+
+    let signature = controller.sign(&said.to_str().as_bytes().to_vec())?;
+    assert!(controller.verify_said_signature(&said, &signature)?);
+    // Signature over message itself isn't signature over SAID.
+    let message_signature = controller.sign(&b"vc".to_vec())?;
+    assert!(!controller.verify_said_signature(&said, &message_signature)?);
+
+    // Vc which wasn't issued.
+    let other_said: SelfAddressingPrefix = MessageHash::new(b"other vc").into();
+    let other_signature = controller.sign(&other_said.to_str().as_bytes().to_vec())?;
+    assert!(!controller.verify_said_signature(&other_said, &other_signature)?);
+
+    controller.update(UpdateType::Revoke(MessageHash::new(b"vc")))?;
+    assert!(!controller.verify_said_signature(&said, &signature)?);
+    let metrics = controller.metrics();
+    assert_eq!(metrics.verified, 1);
+    assert_eq!(metrics.failed, 3);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_rotate_and_update_backers() -> Result<(), Error> {
+    use crate::{
+        kerl::contains_seal, key_manager::seeded::SeededKeyManager, tel::parse_tel_stream,
+    };
+    use keri::{derivation::basic::Basic, signer::CryptoBox};
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let mut controller = Controller::init(SeededKeyManager::new(), dir.path())?;
+    controller.update(UpdateType::Issue("vc1".into()))?;
+    let old_signature = controller.sign(&b"vc1".to_vec())?;
+    let old_key = Basic::Ed25519.derive(controller.key_manager.public_key());
+    let kel_sn = controller.kerl.get_state()?.unwrap().sn;
+
+    let backer = IdentifierPrefix::Basic(Basic::Ed25519.derive(CryptoBox::new()?.public_key()));
+    controller.rotate_and_update_backers(&[backer.clone()], &[])?;
+
+    // Single KEL rotation event anchors the tel rotation.
+    let state = controller.kerl.get_state()?.unwrap();
+    assert_eq!(state.sn, kel_sn + 1);
+    let new_key = Basic::Ed25519.derive(controller.key_manager.public_key());
+    assert_ne!(new_key, old_key);
+    assert_eq!(state.current.public_keys, vec![new_key]);
+    let kel_rot = controller
+        .kerl
+        .get_signed_event_at_sn(&state.prefix, state.sn)?
+        .unwrap();
+    assert!(matches!(
+        kel_rot.event_message.event.event_data,
+        EventData::Rot(_)
+    ));
+
+    let registry = controller.tel.get_management_tel_state()?;
+    assert_eq!(registry.sn, 1);
+    let management_events =
+        String::from_utf8(controller.tel.get_management_events()?.unwrap()).unwrap();
+    assert!(management_events.contains(&backer.to_str()));
+    let tel_rot = parse_tel_stream(management_events.as_bytes())?
+        .pop()
+        .unwrap();
+    assert_eq!(tel_rot.seal.seal.sn, state.sn);
+    assert!(contains_seal(&kel_rot.event_message, &tel_rot.event)?);
+
+    // Vcs are verified with keys from their issuance.
+    assert!(controller.verify("vc1", &old_signature)?);
+    controller.update(UpdateType::Issue("vc2".into()))?;
+    assert!(controller.verify("vc2", &controller.sign(&b"vc2".to_vec())?)?);
+
+    // Key manager rotated outside of KEL makes KEL reject next rotation,
+    // after which key manager is restored.
+    Arc::get_mut(&mut controller.key_manager)
+        .unwrap()
+        .rotate()?;
+    let seeds = controller.key_manager.seeds();
+    assert!(controller
+        .rotate_and_update_backers(&[], &[backer])
+        .is_err());
+    assert_eq!(controller.key_manager.seeds(), seeds);
+    assert_eq!(controller.kerl.get_state()?.unwrap().sn, state.sn + 1);
+    assert_eq!(controller.tel.get_management_tel_state()?.sn, 1);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_batch_seal_order() -> Result<(), Error> {
+    use crate::{kerl::contains_seal, test_support::fresh_controller};
+
+    let (controller, _dir) = fresh_controller();
+    let hashes: Vec<SelfAddressingPrefix> = ["vc1", "vc2", "vc3"]
+        .iter()
+        .map(|vc| MessageHash::new(vc.as_bytes()).into())
+        .collect();
+    let events = hashes
+        .iter()
+        .map(|hash| controller.tel.make_issuance_event_for(hash.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let ixn = controller.anchor_tel_events(&events)?;
+    let source_seal = to_source_seal(&ixn)?;
+    for event in events {
+        controller.tel.process(event, source_seal.clone())?;
+    }
+
+    // Each issuance resolves to its own seal of the batch.
+    for (index, hash) in hashes.iter().enumerate() {
+        let proof = controller.verify_anchoring(hash)?;
+        assert_eq!(proof.kel_event_sn, source_seal.sn);
+        assert_eq!(proof.seal_index, index);
+    }
+
+    // Batch which seals the same tel event twice is rejected.
+    let iss = controller.tel.make_issuance_event(b"vc4")?;
+    let seal = to_event_seal(&iss)?;
+    let ixn = controller
+        .kerl
+        .make_ixn_with_seal(&[seal.clone(), seal], &*controller.key_manager)?;
+    assert!(matches!(
+        contains_seal(&ixn.event_message, &iss),
+        Err(Error::Anchoring(AnchoringFailure::DuplicateEventSeal(sn))) if sn == ixn.event_message.event.sn
+    ));
+    controller.tel.process(iss, to_source_seal(&ixn)?)?;
+    assert!(matches!(
+        controller.verify_anchoring(&MessageHash::new(b"vc4").into()),
+        Err(Error::Anchoring(AnchoringFailure::DuplicateEventSeal(_)))
+    ));
+
+    // Batch which doesn't seal the tel event.
+    let iss = controller.tel.make_issuance_event(b"vc5")?;
+    let ixn = controller.anchor_tel_events(&[])?;
+    controller.tel.process(iss, to_source_seal(&ixn)?)?;
+    assert!(matches!(
+        controller.verify_anchoring(&MessageHash::new(b"vc5").into()),
+        Err(Error::Anchoring(AnchoringFailure::MissingEventSeal(_)))
+    ));
 
     Ok(())
 }
@@ -0,0 +1,62 @@
+use std::sync::Mutex;
+
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use keri::prefix::{IdentifierPrefix, SelfAddressingPrefix};
+use teliox::event::Event;
+
+use crate::tel::{is_issuance, is_revocation};
+
+// Change of registry state, made by completed mutating operation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RegistryEvent {
+    Issued(SelfAddressingPrefix),
+    Revoked(SelfAddressingPrefix),
+    // Sn of management tel rotation event.
+    Rotated(u64),
+}
+
+impl RegistryEvent {
+    pub fn from_tel_event(event: &Event) -> Option<Self> {
+        match (event, event.get_prefix()) {
+            (Event::Vc(_), IdentifierPrefix::SelfAddressing(hash)) if is_issuance(event) => {
+                Some(RegistryEvent::Issued(hash))
+            }
+            (Event::Vc(_), IdentifierPrefix::SelfAddressing(hash)) if is_revocation(event) => {
+                Some(RegistryEvent::Revoked(hash))
+            }
+            // Management events after inception are rotations.
+            (Event::Management(_), _) if event.get_sn() > 0 => {
+                Some(RegistryEvent::Rotated(event.get_sn()))
+            }
+            _ => None,
+        }
+    }
+}
+
+// Channels of registry events subscribers. Every subscriber gets events
+// notified after it subscribed.
+#[derive(Debug, Default)]
+pub struct Subscribers {
+    senders: Mutex<Vec<Sender<RegistryEvent>>>,
+}
+
+impl Subscribers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self) -> Receiver<RegistryEvent> {
+        let (sender, receiver) = unbounded();
+        self.senders.lock().unwrap().push(sender);
+        receiver
+    }
+
+    // Sends event to all subscribers. Subscribers whose receivers were
+    // dropped are removed.
+    pub fn notify(&self, event: RegistryEvent) {
+        self.senders
+            .lock()
+            .unwrap()
+            .retain(|sender| sender.send(event.clone()).is_ok());
+    }
+}
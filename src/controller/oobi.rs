@@ -0,0 +1,153 @@
+use keri::{
+    event_message::parse::{signed_event_stream, Deserialized},
+    prefix::{IdentifierPrefix, Prefix},
+    signer::KeyManager,
+};
+use serde_json::{json, Value};
+use tempfile::tempdir;
+
+use super::{signature::Signature, Controller};
+use crate::{
+    error::Error,
+    kerl::{verify_signatures, KERL},
+};
+
+impl<K: KeyManager + Send + Sync> Controller<K> {
+    // Returns OOBI with endpoint role reply, signed by controller, which
+    // states that controller's KEL can be fetched from `endpoint_url`.
+    pub fn generate_oobi(&self, endpoint_url: &str) -> Result<String, Error> {
+        let prefix = self.kerl.get_prefix().to_str();
+        let reply = json!({
+            "r": "/end/role/add",
+            "a": {
+                "cid": prefix,
+                "role": "controller",
+                "eid": prefix,
+                "url": endpoint_url,
+            }
+        });
+        let signature =
+            self.sign(&serde_json::to_vec(&reply).map_err(|e| Error::Generic(e.to_string()))?)?;
+        Ok(json!({
+            "oobi": format!(
+                "{}/oobi/{}/controller",
+                endpoint_url.trim_end_matches('/'),
+                prefix
+            ),
+            "rpy": reply,
+            "sig": signature.to_attached().to_str(),
+        })
+        .to_string())
+    }
+
+    // Checks if all events of `fetched_kel` belong to identifier from OOBI
+    // and verifies OOBI reply signature against the identifier's current
+    // keys. Fetched KEL is processed into scratch database first, and it's
+    // processed into controller's database only when signature is valid.
+    pub fn resolve_oobi(&self, oobi: &str, fetched_kel: &[u8]) -> Result<IdentifierPrefix, Error> {
+        let oobi: Value = serde_json::from_str(oobi).map_err(|e| Error::Parse(e.to_string()))?;
+        let reply = &oobi["rpy"];
+        let prefix: IdentifierPrefix = reply["a"]["cid"]
+            .as_str()
+            .ok_or(Error::Parse("Missing OOBI identifier".into()))?
+            .parse()?;
+        let signature = Signature::from_bytes(
+            oobi["sig"]
+                .as_str()
+                .ok_or(Error::Parse("Missing OOBI signature".into()))?
+                .as_bytes(),
+        )?;
+
+        let events = signed_event_stream(fetched_kel)
+            .map_err(|e| Error::Parse(e.to_string()))?
+            .1;
+        let matches_prefix = events.iter().all(|event| match event {
+            Deserialized::Event(ev) => ev.event.event.event.prefix == prefix,
+            _ => true,
+        });
+        if events.is_empty() || !matches_prefix {
+            return Err(Error::Generic("OOBI prefix doesn't match KEL".into()));
+        }
+        let scratch_dir = tempdir()?;
+        let scratch = KERL::new(scratch_dir.path())?;
+        scratch.process_kel(fetched_kel)?;
+        let state = scratch
+            .get_state_for_prefix(&prefix)?
+            .ok_or(Error::Generic("OOBI prefix doesn't match KEL".into()))?;
+        let reply = serde_json::to_vec(reply).map_err(|e| Error::Generic(e.to_string()))?;
+        if !verify_signatures(&state, &reply, &[signature.to_attached()])? {
+            return Err(Error::Generic("Invalid OOBI signature".into()));
+        }
+        self.kerl.process_kel(fetched_kel)?;
+        Ok(prefix)
+    }
+}
+
+#[test]
+pub fn test_oobi() -> Result<(), Error> {
+    use crate::test_support::fresh_controller;
+
+    let (issuer, _issuer_dir) = fresh_controller();
+    let (verifier, _verifier_dir) = fresh_controller();
+
+    let oobi = issuer.generate_oobi("http://localhost:3232")?;
+    assert!(oobi.contains(&format!(
+        "http://localhost:3232/oobi/{}/controller",
+        issuer.kerl.get_prefix().to_str()
+    )));
+
+    // KEL of other identifier.
+    let verifier_kel = verifier.get_kerl()?.unwrap();
+    assert!(verifier.resolve_oobi(&oobi, &verifier_kel).is_err());
+
+    let issuer_kel = issuer.get_kerl()?.unwrap();
+    let resolved = verifier.resolve_oobi(&oobi, &issuer_kel)?;
+    assert_eq!(resolved, issuer.kerl.get_prefix());
+    assert!(verifier
+        .kerl
+        .get_state_for_prefix(&issuer.kerl.get_prefix())?
+        .is_some());
+
+    // OOBI with forged signature doesn't get its KEL ingested.
+    let (forger, _forger_dir) = fresh_controller();
+    let (other_verifier, _other_verifier_dir) = fresh_controller();
+    let mut forged: Value = serde_json::from_str(&oobi).unwrap();
+    let forged_signature = forger.sign(&serde_json::to_vec(&forged["rpy"]).unwrap())?;
+    forged["sig"] = json!(forged_signature.to_attached().to_str());
+    assert!(other_verifier
+        .resolve_oobi(&forged.to_string(), &issuer_kel)
+        .is_err());
+    assert!(other_verifier
+        .kerl
+        .get_state_for_prefix(&issuer.kerl.get_prefix())?
+        .is_none());
+
+    Ok(())
+}
+
+#[test]
+pub fn test_oobi_multisig() -> Result<(), Error> {
+    use crate::test_support::{fresh_controller, fresh_multisig_controller};
+    use std::sync::Arc;
+
+    // Identifier with 1 of 2 signing threshold, which signs with second key.
+    let (first, km2, dir) = fresh_multisig_controller(1);
+    let issuer = Controller::from_parts(
+        km2,
+        Arc::clone(&first.kerl),
+        Arc::clone(&first.tel),
+        dir.path(),
+    );
+    let (verifier, _verifier_dir) = fresh_controller();
+
+    // Signature index is carried in OOBI.
+    let oobi = issuer.generate_oobi("http://localhost:3232")?;
+    let sig: Value = serde_json::from_str::<Value>(&oobi).unwrap()["sig"].clone();
+    let signature = Signature::from_bytes(sig.as_str().unwrap().as_bytes())?;
+    assert_eq!(signature.index, 1);
+
+    let resolved = verifier.resolve_oobi(&oobi, &issuer.get_kerl()?.unwrap())?;
+    assert_eq!(resolved, issuer.kerl.get_prefix());
+
+    Ok(())
+}
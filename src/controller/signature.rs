@@ -0,0 +1,75 @@
+use std::str::FromStr;
+
+use keri::{
+    derivation::self_signing::SelfSigning,
+    prefix::{AttachedSignaturePrefix, Prefix},
+};
+
+use crate::error::Error;
+
+// Signature of vc along with derivation code and index of signing key in
+// current key list of issuer, so verifier doesn't need to guess them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub code: SelfSigning,
+    pub index: u16,
+    pub bytes: Vec<u8>,
+}
+
+impl Signature {
+    pub fn new(code: SelfSigning, index: u16, bytes: Vec<u8>) -> Self {
+        Self { code, index, bytes }
+    }
+
+    // Serializes signature as qualified attached signature, e.g. `AA...`
+    // for Ed25519 signature made with the first key.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_attached().to_str().into_bytes()
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        let s = std::str::from_utf8(data).map_err(|e| Error::Parse(e.to_string()))?;
+        AttachedSignaturePrefix::from_str(s)
+            .map(Self::from)
+            .map_err(|_| Error::Parse(format!("Invalid signature: {}", s)))
+    }
+
+    pub(crate) fn to_attached(&self) -> AttachedSignaturePrefix {
+        AttachedSignaturePrefix::new(self.code, self.bytes.clone(), self.index)
+    }
+}
+
+impl From<AttachedSignaturePrefix> for Signature {
+    fn from(attached: AttachedSignaturePrefix) -> Self {
+        Self {
+            code: attached.signature.derivation,
+            index: attached.index,
+            bytes: attached.signature.signature,
+        }
+    }
+}
+
+#[test]
+pub fn test_signature_bytes() -> Result<(), Error> {
+    use keri::signer::{CryptoBox, KeyManager};
+
+    let km = CryptoBox::new()?;
+    for index in &[0, 1, 63] {
+        let signature = Signature::new(
+            SelfSigning::Ed25519Sha512,
+            *index,
+            km.sign(&b"vc".to_vec())?,
+        );
+        let bytes = signature.to_bytes();
+        assert_eq!(Signature::from_bytes(&bytes)?, signature);
+    }
+
+    for invalid in &[&b""[..], b"not a signature", &[0xff, 0xfe]] {
+        assert!(matches!(
+            Signature::from_bytes(invalid),
+            Err(Error::Parse(_))
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use keri::{
+    prefix::{IdentifierPrefix, SelfAddressingPrefix},
+    state::IdentifierState,
+};
+use teliox::{
+    event::{verifiable_event::VerifiableEvent, Event},
+    state::vc_state::TelState,
+};
+
+use super::signing_state_at_seal;
+use crate::{error::Error, kerl::KERL, tel::Tel};
+
+// Read only view of KEL and TEL at the moment of its creation. It shares
+// databases with controller, so taking it doesn't copy any events, and
+// queries don't need any of the controller locks.
+//
+// Point in time is set by the sn of last KEL event. Tel events anchored in
+// later KEL events are filtered out, so issuances and revocations made after
+// snapshot creation are not visible in it.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    kerl: Arc<KERL>,
+    tel: Arc<Tel>,
+    sn: u64,
+}
+
+impl Snapshot {
+    pub fn new(kerl: Arc<KERL>, tel: Arc<Tel>) -> Result<Self, Error> {
+        let sn = kerl
+            .get_state()?
+            .ok_or(Error::Generic("Identifier is not incepted".into()))?
+            .sn;
+        Ok(Self { kerl, tel, sn })
+    }
+
+    // Creates view of KEL and TEL as they were after KEL event with given
+    // sn.
+    pub fn at_sn(kerl: Arc<KERL>, tel: Arc<Tel>, sn: u64) -> Result<Self, Error> {
+        let current_sn = kerl
+            .get_state()?
+            .ok_or(Error::Generic("Identifier is not incepted".into()))?
+            .sn;
+        if sn > current_sn {
+            return Err(Error::Generic(format!(
+                "Sn {} is greater than sn of last KEL event {}",
+                sn, current_sn
+            )));
+        }
+        Ok(Self { kerl, tel, sn })
+    }
+
+    // Returns sn of last KEL event visible in snapshot.
+    pub fn sn(&self) -> u64 {
+        self.sn
+    }
+
+    pub fn get_kel(&self) -> Result<Vec<u8>, Error> {
+        let mut kel = vec![];
+        self.kerl.write_kerl_to_sn(self.sn, &mut kel)?;
+        Ok(kel)
+    }
+
+    // Returns events of vc tel which are anchored in KEL events visible in
+    // snapshot.
+    pub fn get_tel(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+    ) -> Result<Vec<VerifiableEvent>, Error> {
+        self.tel
+            .iter_tel(message_hash)
+            .take_while(|event| match event {
+                Ok(event) => event.seal.seal.sn <= self.sn,
+                Err(_) => true,
+            })
+            .collect()
+    }
+
+    pub fn get_vc_state(&self, message_hash: &SelfAddressingPrefix) -> Result<TelState, Error> {
+        self.get_tel(message_hash)?
+            .into_iter()
+            .try_fold(TelState::NotIsuued, |state, event| match event.event {
+                Event::Vc(vc_event) => state.apply(&vc_event).map_err(Error::from),
+                Event::Management(_) => Ok(state),
+            })
+    }
+
+    // Returns issuer state from the moment of issuance of vc with given
+    // hash, found with source seal of its issuance event. Re-anchorings
+    // aren't taken into account, since only the last one is kept.
+    pub fn get_signing_state(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+    ) -> Result<IdentifierState, Error> {
+        let source_seal = self
+            .get_tel(message_hash)?
+            .into_iter()
+            .next()
+            .ok_or(Error::Generic("Missing issuance event".into()))?
+            .seal
+            .seal;
+        signing_state_at_seal(&self.kerl, &self.tel.get_issuer()?, &source_seal)
+    }
+
+    // Returns sn of last establishment event of identifier at or before
+    // `sn`. Events after snapshot sn aren't visible.
+    pub fn get_establishment_sn(&self, id: &IdentifierPrefix, sn: u64) -> Result<u64, Error> {
+        if sn > self.sn {
+            return Err(Error::Generic(format!(
+                "Sn {} is greater than snapshot sn {}",
+                sn, self.sn
+            )));
+        }
+        self.kerl.get_establishment_sn(id, sn)
+    }
+}
@@ -0,0 +1,254 @@
+use keri::{
+    event::event_data::EventData,
+    event_message::SignedEventMessage,
+    prefix::{IdentifierPrefix, Prefix, SelfAddressingPrefix},
+    signer::KeyManager,
+    state::IdentifierState,
+};
+use teliox::{
+    event::{vc_event::VCEvent, verifiable_event::VerifiableEvent, Event},
+    state::vc_state::TelState,
+};
+
+use super::{anchoring::AnchoringFailure, Controller};
+use crate::{
+    error::Error,
+    kerl::{contains_seal, verify_signatures},
+    tel::{is_issuance, is_revocation},
+};
+
+// Tel event of vc with KEL event anchoring it and establishment event which
+// set keys signing the anchoring event.
+#[derive(Debug)]
+pub struct AnchoredTelEvent {
+    // Tel event with source seal of anchoring event.
+    pub tel_event: VerifiableEvent,
+    pub anchoring_event: SignedEventMessage,
+    pub establishment_event: SignedEventMessage,
+}
+
+impl AnchoredTelEvent {
+    // Returns serialized tel event followed by KEL events.
+    pub fn serialize(&self) -> Result<Vec<u8>, Error> {
+        Ok([
+            self.tel_event.serialize()?,
+            self.anchoring_event.serialize()?,
+            self.establishment_event.serialize()?,
+        ]
+        .concat())
+    }
+}
+
+// Compact hint of current state of single vc: its issuance and, if revoked,
+// revocation, each with KEL events anchoring it. Proof doesn't show that no
+// later establishment event precedes the anchoring one, which needs the
+// whole KEL, so it isn't authoritative.
+#[derive(Debug)]
+pub struct StatusProof {
+    pub issuance: AnchoredTelEvent,
+    pub revocation: Option<AnchoredTelEvent>,
+}
+
+impl StatusProof {
+    // Returns serialized issuance followed by revocation.
+    pub fn serialize(&self) -> Result<Vec<u8>, Error> {
+        let mut serialized = self.issuance.serialize()?;
+        if let Some(revocation) = &self.revocation {
+            serialized.extend(revocation.serialize()?);
+        }
+        Ok(serialized)
+    }
+}
+
+impl<K: KeyManager + Send + Sync> Controller<K> {
+    // Returns compact proof of current state of vc with given hash.
+    pub fn status_proof(&self, message_hash: &SelfAddressingPrefix) -> Result<StatusProof, Error> {
+        let mut tel = self.tel.get_tel(message_hash)?.into_iter();
+        let issuance = tel.next().ok_or(AnchoringFailure::MissingTelEvent)?;
+        let revocation = tel.last().filter(|event| is_revocation(&event.event));
+        Ok(StatusProof {
+            issuance: self.anchored_tel_event(issuance)?,
+            revocation: revocation
+                .map(|event| self.anchored_tel_event(event))
+                .transpose()?,
+        })
+    }
+
+    fn anchored_tel_event(&self, tel_event: VerifiableEvent) -> Result<AnchoredTelEvent, Error> {
+        let issuer = self.tel.get_issuer()?;
+        let sn = tel_event.seal.seal.sn;
+        let anchoring_event = self
+            .kerl
+            .get_signed_event_at_sn(&issuer, sn)?
+            .ok_or(AnchoringFailure::MissingKelEvent(sn))?;
+        let establishment_sn = self.kerl.get_establishment_sn(&issuer, sn)?;
+        let establishment_event = self
+            .kerl
+            .get_signed_event_at_sn(&issuer, establishment_sn)?
+            .ok_or(AnchoringFailure::MissingKelEvent(establishment_sn))?;
+        Ok(AnchoredTelEvent {
+            tel_event,
+            anchoring_event,
+            establishment_event,
+        })
+    }
+}
+
+// Checks chains of status proof, from tel events to keys of establishment
+// events of expected issuer, and returns state of vc set by applying
+// revocation to issuance. It's only a hint: keys could have been rotated
+// before anchoring, which only the whole KEL of issuer shows.
+pub fn verify_status_hint(
+    proof: &StatusProof,
+    issuer: &IdentifierPrefix,
+) -> Result<TelState, Error> {
+    if !is_issuance(&proof.issuance.tel_event.event) {
+        return Err(Error::Generic("Not an issuance event".into()));
+    }
+    let state = TelState::NotIsuued.apply(verify_anchored(&proof.issuance, issuer)?)?;
+    match &proof.revocation {
+        Some(revocation) if is_revocation(&revocation.tel_event.event) => {
+            if revocation.tel_event.event.get_prefix()
+                != proof.issuance.tel_event.event.get_prefix()
+            {
+                return Err(Error::Generic("Revocation of other vc".into()));
+            }
+            Ok(state.apply(verify_anchored(revocation, issuer)?)?)
+        }
+        Some(_) => Err(Error::Generic("Not a revocation event".into())),
+        None => Ok(state),
+    }
+}
+
+// Checks chain from tel event to keys of establishment event of expected
+// issuer, and returns the vc event.
+fn verify_anchored<'a>(
+    anchored: &'a AnchoredTelEvent,
+    issuer: &IdentifierPrefix,
+) -> Result<&'a VCEvent, Error> {
+    let seal = &anchored.tel_event.seal.seal;
+    let anchoring = &anchored.anchoring_event.event_message;
+    if &anchoring.event.prefix != issuer {
+        return Err(Error::Generic(format!(
+            "Proof is anchored in KEL of {}, not of issuer {}",
+            anchoring.event.prefix.to_str(),
+            issuer.to_str()
+        )));
+    }
+    if anchoring.event.sn != seal.sn || !seal.digest.verify_binding(&anchoring.serialize()?) {
+        return Err(AnchoringFailure::SourceSealMismatch(seal.sn).into());
+    }
+    if !contains_seal(anchoring, &anchored.tel_event.event)? {
+        return Err(AnchoringFailure::MissingEventSeal(seal.sn).into());
+    }
+
+    let establishment = &anchored.establishment_event.event_message;
+    if establishment.event.prefix != anchoring.event.prefix
+        || establishment.event.sn > anchoring.event.sn
+    {
+        return Err(Error::Generic(
+            "Establishment event doesn't precede anchoring event".into(),
+        ));
+    }
+    let key_config = match &establishment.event.event_data {
+        EventData::Icp(icp) => icp.key_config.clone(),
+        EventData::Rot(rot) => rot.key_config.clone(),
+        _ => return Err(Error::Generic("Not an establishment event".into())),
+    };
+    let state = IdentifierState {
+        current: key_config,
+        ..IdentifierState::default()
+    };
+    // Establishment event is signed by keys it sets, as well as events
+    // following it.
+    for event in &[&anchored.establishment_event, &anchored.anchoring_event] {
+        if !verify_signatures(&state, &event.event_message.serialize()?, &event.signatures)? {
+            return Err(Error::Generic(format!(
+                "Invalid signatures of KEL event at sn {}",
+                event.event_message.event.sn
+            )));
+        }
+    }
+
+    match &anchored.tel_event.event {
+        Event::Vc(vc) => Ok(vc),
+        _ => Err(Error::Generic("Not a vc event".into())),
+    }
+}
+
+#[test]
+pub fn test_status_proof() -> Result<(), Error> {
+    use crate::{
+        controller::{MessageHash, UpdateType},
+        test_support::fresh_controller,
+    };
+
+    let (controller, _dir) = fresh_controller();
+    let issuer = controller.issuer_prefix();
+    for vc in &["vc", "other vc", "another vc"] {
+        controller.update(UpdateType::Issue(vc.to_string()))?;
+    }
+    let message_hash: SelfAddressingPrefix = MessageHash::new(b"vc").into();
+
+    let proof = controller.status_proof(&message_hash)?;
+    assert!(proof.revocation.is_none());
+    assert!(matches!(
+        verify_status_hint(&proof, &issuer)?,
+        TelState::Issued(_)
+    ));
+
+    let mut full = controller.get_kerl()?.unwrap();
+    full.extend(controller.tel.get_management_events()?.unwrap());
+    controller.tel.write_tel(&message_hash, &mut full)?;
+    assert!(proof.serialize()?.len() < full.len());
+
+    controller.update(UpdateType::Revoke(MessageHash::new(b"vc")))?;
+    let proof = controller.status_proof(&message_hash)?;
+    assert!(matches!(
+        verify_status_hint(&proof, &issuer)?,
+        TelState::Revoked
+    ));
+
+    // Revocation anchored in anchoring event of other tel event.
+    let other_proof = controller.status_proof(&MessageHash::new(b"other vc").into())?;
+    let forged = StatusProof {
+        issuance: controller.status_proof(&message_hash)?.issuance,
+        revocation: Some(AnchoredTelEvent {
+            anchoring_event: other_proof.issuance.anchoring_event,
+            ..proof.revocation.unwrap()
+        }),
+    };
+    assert!(verify_status_hint(&forged, &issuer).is_err());
+
+    // Revocation of other vc doesn't revoke this one.
+    controller.update(UpdateType::Revoke(MessageHash::new(b"other vc")))?;
+    let other_revocation = controller
+        .status_proof(&MessageHash::new(b"other vc").into())?
+        .revocation;
+    let unrelated = StatusProof {
+        issuance: controller
+            .status_proof(&MessageHash::new(b"another vc").into())?
+            .issuance,
+        revocation: other_revocation,
+    };
+    assert!(verify_status_hint(&unrelated, &issuer).is_err());
+
+    // Bare revocation is not a proof.
+    let bare = StatusProof {
+        issuance: controller.status_proof(&message_hash)?.revocation.unwrap(),
+        revocation: None,
+    };
+    assert!(verify_status_hint(&bare, &issuer).is_err());
+
+    // Valid proof of the same vc issued by other identifier.
+    let (other_controller, _other_dir) = fresh_controller();
+    other_controller.update(UpdateType::Issue("vc".into()))?;
+    let other_issuer_proof = other_controller.status_proof(&message_hash)?;
+    assert!(matches!(
+        verify_status_hint(&other_issuer_proof, &other_controller.issuer_prefix())?,
+        TelState::Issued(_)
+    ));
+    assert!(verify_status_hint(&other_issuer_proof, &issuer).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,253 @@
+use std::sync::{Arc, Mutex};
+
+use keri::{
+    derivation::self_addressing::SelfAddressing, prefix::SelfAddressingPrefix,
+    state::IdentifierState,
+};
+use teliox::state::vc_state::TelState;
+use tempfile::tempdir;
+
+use super::{resolve_db_paths, resolve_signing_state, signature::Signature};
+use crate::{
+    error::Error,
+    kerl::{verify_signatures, KERL},
+    tel::Tel,
+};
+
+// Verifies many signatures made with keys of one vc, resolving issuer state
+// only once. Any new event of issuer's KEL, e.g. rotation or anchoring of
+// revocation, makes resolved state outdated, so it is resolved again.
+#[derive(Debug)]
+pub struct CredentialVerifier {
+    kerl: Arc<KERL>,
+    tel: Arc<Tel>,
+    message_hash: SelfAddressingPrefix,
+    // Sn of issuer's KEL at the moment of resolution, with signing state of
+    // vc. State is missing if vc is not issued.
+    resolved: Mutex<(u64, Option<IdentifierState>)>,
+}
+
+impl CredentialVerifier {
+    pub fn new(
+        kerl: Arc<KERL>,
+        tel: Arc<Tel>,
+        message_hash: SelfAddressingPrefix,
+    ) -> Result<Self, Error> {
+        let verifier = Self {
+            kerl,
+            tel,
+            message_hash,
+            resolved: Mutex::new((0, None)),
+        };
+        let sn = verifier.issuer_sn()?;
+        *verifier.resolved.lock().unwrap() = (sn, verifier.resolve()?);
+        Ok(verifier)
+    }
+
+    // Checks if vc is issued and signature of message was made with keys
+    // which were current at its issuance.
+    pub fn verify(&self, message: &[u8], signature: &Signature) -> Result<bool, Error> {
+        match self.signing_state()? {
+            Some(state) => verify_signatures(&state, message, &[signature.to_attached()]),
+            None => Ok(false),
+        }
+    }
+
+    // Returns sn of issuer's KEL, at which signing state was resolved.
+    pub fn resolved_at(&self) -> u64 {
+        self.resolved.lock().unwrap().0
+    }
+
+    fn signing_state(&self) -> Result<Option<IdentifierState>, Error> {
+        let sn = self.issuer_sn()?;
+        let mut resolved = self.resolved.lock().unwrap();
+        if resolved.0 != sn {
+            *resolved = (sn, self.resolve()?);
+        }
+        Ok(resolved.1.clone())
+    }
+
+    fn resolve(&self) -> Result<Option<IdentifierState>, Error> {
+        if !matches!(
+            self.tel.get_vc_state(&self.message_hash)?,
+            TelState::Issued(_)
+        ) {
+            return Ok(None);
+        }
+        resolve_signing_state(
+            &self.kerl,
+            &self.tel,
+            &self.tel.get_issuer()?,
+            &self.message_hash,
+        )
+        .map(Some)
+    }
+
+    fn issuer_sn(&self) -> Result<u64, Error> {
+        Ok(self
+            .kerl
+            .get_state()?
+            .ok_or(Error::Generic("Identifier is not incepted".into()))?
+            .sn)
+    }
+}
+
+// Verifies message of issuer using only exported streams: issuer's KEL and
+// TEL with management events followed by events of vc. Tel events not
+// anchored in the KEL are rejected. Message hash is computed with given
+// derivation, which has to be the one issuer used, see
+// `Controller::with_derivation`.
+//
+// Streams are processed into databases in temporary directory, removed after
+// verification, so relying party keeps no state. Databases are always on
+// disk, because KERL and Tel have no in-memory storage.
+pub fn verify_with_kel(
+    kel: &[u8],
+    tel: &[u8],
+    message: &[u8],
+    signature: &Signature,
+    derivation: SelfAddressing,
+) -> Result<bool, Error> {
+    let dir = tempdir()?;
+    let (kel_path, tel_path) = resolve_db_paths(dir.path());
+    let kerl = KERL::new(&kel_path)?;
+    kerl.process_kel(kel)?;
+    let mut vc_tel = Tel::new(&tel_path)?;
+    vc_tel.ingest(tel, &kerl)?;
+
+    let message_hash = derivation.derive(message);
+    if !matches!(vc_tel.get_vc_state(&message_hash)?, TelState::Issued(_)) {
+        return Ok(false);
+    }
+    let state = resolve_signing_state(&kerl, &vc_tel, &vc_tel.get_issuer()?, &message_hash)?;
+    verify_signatures(&state, message, &[signature.to_attached()])
+}
+
+#[test]
+pub fn test_credential_verifier() -> Result<(), Error> {
+    use crate::{
+        controller::{MessageHash, UpdateType},
+        test_support::fresh_controller,
+    };
+
+    let (mut controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc".into()))?;
+    let verifier = controller.make_verifier(&MessageHash::new(b"vc").into())?;
+    let resolved_at = verifier.resolved_at();
+
+    for message in &["first", "second", "third"] {
+        let signature = controller.sign(&message.as_bytes().to_vec())?;
+        assert!(verifier.verify(message.as_bytes(), &signature)?);
+        assert!(!verifier.verify(b"other", &signature)?);
+    }
+    // State wasn't resolved again.
+    assert_eq!(verifier.resolved_at(), resolved_at);
+
+    // After rotation state is resolved again, but keys from issuance are
+    // still used.
+    let old_signature = controller.sign(&b"message".to_vec())?;
+    Arc::get_mut(&mut controller.key_manager)
+        .unwrap()
+        .rotate()?;
+    controller.kerl.rotate(controller.key_manager.as_ref())?;
+    assert!(verifier.verify(b"message", &old_signature)?);
+    assert!(verifier.resolved_at() > resolved_at);
+    assert!(!verifier.verify(b"message", &controller.sign(&b"message".to_vec())?)?);
+
+    controller.update(UpdateType::Revoke(MessageHash::new(b"vc")))?;
+    assert!(!verifier.verify(b"message", &old_signature)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_verify_with_kel() -> Result<(), Error> {
+    use crate::{
+        controller::{MessageHash, UpdateType},
+        test_support::fresh_controller,
+    };
+
+    let (controller, _dir) = fresh_controller();
+    controller.update(UpdateType::Issue("vc".into()))?;
+    let signature = controller.sign(&b"vc".to_vec())?;
+    let export = || -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let mut tel = controller.tel.get_management_events()?.unwrap();
+        controller
+            .tel
+            .write_tel(&MessageHash::new(b"vc").into(), &mut tel)?;
+        Ok((controller.get_kerl()?.unwrap(), tel))
+    };
+
+    let (kel, tel) = export()?;
+    assert!(verify_with_kel(
+        &kel,
+        &tel,
+        b"vc",
+        &signature,
+        SelfAddressing::Blake3_256
+    )?);
+    assert!(!verify_with_kel(
+        &kel,
+        &tel,
+        b"other",
+        &signature,
+        SelfAddressing::Blake3_256
+    )?);
+    // Vc isn't found under hash of other derivation.
+    assert!(!verify_with_kel(
+        &kel,
+        &tel,
+        b"vc",
+        &signature,
+        SelfAddressing::SHA3_256
+    )?);
+
+    // Tel isn't anchored in KEL of other identifier.
+    let (other, _other_dir) = fresh_controller();
+    assert!(verify_with_kel(
+        &other.get_kerl()?.unwrap(),
+        &tel,
+        b"vc",
+        &signature,
+        SelfAddressing::Blake3_256
+    )
+    .is_err());
+
+    controller.update(UpdateType::Revoke(MessageHash::new(b"vc")))?;
+    let (kel, tel) = export()?;
+    assert!(!verify_with_kel(
+        &kel,
+        &tel,
+        b"vc",
+        &signature,
+        SelfAddressing::Blake3_256
+    )?);
+
+    // Vc issued with other derivation.
+    let (controller, _dir) = fresh_controller();
+    let controller = controller.with_derivation(SelfAddressing::SHA3_256);
+    controller.update(UpdateType::Issue("vc".into()))?;
+    let signature = controller.sign(&b"vc".to_vec())?;
+    let mut tel = controller.tel.get_management_events()?.unwrap();
+    controller.tel.write_tel(
+        &MessageHash::new_with(b"vc", SelfAddressing::SHA3_256).into(),
+        &mut tel,
+    )?;
+    let kel = controller.get_kerl()?.unwrap();
+    assert!(verify_with_kel(
+        &kel,
+        &tel,
+        b"vc",
+        &signature,
+        SelfAddressing::SHA3_256
+    )?);
+    assert!(!verify_with_kel(
+        &kel,
+        &tel,
+        b"vc",
+        &signature,
+        SelfAddressing::Blake3_256
+    )?);
+
+    Ok(())
+}
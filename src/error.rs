@@ -1,3 +1,6 @@
+use std::path::{Path, PathBuf};
+
+use crate::{controller::anchoring::AnchoringFailure, tel::VcStatusKind};
 use keri::error::Error as KeriError;
 use teliox::error::Error as TelError;
 use thiserror::Error;
@@ -13,9 +16,60 @@ pub enum Error {
     #[error(transparent)]
     TelError(#[from] TelError),
 
+    #[error(transparent)]
+    SledError(#[from] sled::Error),
+
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+
+    #[error("Can't open database at {path:?}: {source}")]
+    Database {
+        path: PathBuf,
+        source: Box<dyn std::error::Error>,
+    },
+
     #[error("{0}")]
     Generic(String),
 
+    #[error("Parse error: {0}")]
+    Parse(String),
+
+    #[error("Key manager error: {0}")]
+    KeyManagerError(#[source] KeriError),
+
+    #[error("Rejected events: {0:?}")]
+    RejectedEvents(Vec<String>),
+
+    #[error("Anchoring check failed: {0}")]
+    Anchoring(#[from] AnchoringFailure),
+
+    #[error("Identifier is abandoned")]
+    Abandoned,
+
+    #[error("Vc can't be revoked in state {state:?}")]
+    CannotRevoke { state: VcStatusKind },
+
+    #[error("Vc was already issued and is in state {state:?}")]
+    AlreadyIssued { state: VcStatusKind },
+
+    #[error("Tel event anchored in KEL event {kel_sn} was not processed, use repair")]
+    Inconsistent { kel_sn: u64 },
+
+    #[error("Tel of vc is forked at sn {sn}")]
+    Forked { sn: u64 },
+
+    #[error("Event has {received} witness receipts, {required} required")]
+    InsufficientReceipts { required: u64, received: u64 },
+
     #[error("Queue error")]
     QueueError,
 }
+
+impl Error {
+    pub(crate) fn database<E: std::error::Error + 'static>(path: &Path, source: E) -> Self {
+        Error::Database {
+            path: path.to_path_buf(),
+            source: Box::new(source),
+        }
+    }
+}
@@ -1,13 +1,17 @@
+use super::key_prefix;
 use crate::error::Error;
 use keri::{
-    derivation::{basic::Basic, self_addressing::SelfAddressing},
+    derivation::self_addressing::SelfAddressing,
     event::{
         event_data::{EventData, Receipt},
-        sections::seal::{EventSeal, Seal},
+        sections::{
+            seal::{EventSeal, Seal},
+            threshold::SignatureThreshold,
+        },
         Event, EventMessage, SerializationFormats,
     },
     event_message::event_msg_builder::{EventMsgBuilder, EventType},
-    prefix::IdentifierPrefix,
+    prefix::{BasicPrefix, IdentifierPrefix},
     signer::KeyManager,
     state::IdentifierState,
 };
@@ -16,30 +20,144 @@ pub fn make_icp(
     km: &dyn KeyManager,
     prefix: Option<IdentifierPrefix>,
 ) -> Result<EventMessage, Error> {
-    let key_prefix = vec![Basic::Ed25519.derive(km.public_key())];
-    let pref = prefix.unwrap_or(IdentifierPrefix::Basic(key_prefix[0].clone()));
-    let nxt_key_prefix = vec![Basic::Ed25519.derive(km.next_public_key())];
+    let keys = vec![key_prefix(km.public_key())?];
+    let pref = prefix.unwrap_or(IdentifierPrefix::Basic(keys[0].clone()));
+    let next_keys = vec![key_prefix(km.next_public_key())?];
     let icp = EventMsgBuilder::new(EventType::Inception)?
         .with_prefix(pref)
-        .with_keys(key_prefix)
-        .with_next_keys(nxt_key_prefix)
+        .with_keys(keys)
+        .with_next_keys(next_keys)
+        .build()?;
+    Ok(icp)
+}
+
+// Makes inception event which designates witnesses, `tally` of which must
+// receipt events of the identifier.
+pub fn make_icp_with_witnesses(
+    km: &dyn KeyManager,
+    witnesses: &[BasicPrefix],
+    tally: u64,
+) -> Result<EventMessage, Error> {
+    if tally as usize > witnesses.len() {
+        return Err(Error::Generic(format!(
+            "Witness threshold {} exceeds number of witnesses {}",
+            tally,
+            witnesses.len()
+        )));
+    }
+    let icp = EventMsgBuilder::new(EventType::Inception)?
+        .with_keys(vec![key_prefix(km.public_key())?])
+        .with_next_keys(vec![key_prefix(km.next_public_key())?])
+        .with_witness_list(witnesses)
+        .with_witness_threshold(tally)
+        .build()?;
+    Ok(icp)
+}
+
+// Makes inception event with keys of all key managers, in their order.
+pub fn make_icp_with_threshold(
+    kms: &[&dyn KeyManager],
+    threshold: &SignatureThreshold,
+    next_threshold: &SignatureThreshold,
+) -> Result<EventMessage, Error> {
+    let keys: Vec<_> = kms
+        .iter()
+        .map(|km| key_prefix(km.public_key()))
+        .collect::<Result<_, _>>()?;
+    let next_keys: Vec<_> = kms
+        .iter()
+        .map(|km| key_prefix(km.next_public_key()))
+        .collect::<Result<_, _>>()?;
+    validate_threshold(threshold, keys.len())?;
+    validate_threshold(next_threshold, next_keys.len())?;
+    let icp = EventMsgBuilder::new(EventType::Inception)?
+        .with_keys(keys)
+        .with_threshold(threshold)
+        .with_next_keys(next_keys)
+        .with_next_threshold(next_threshold)
         .build()?;
     Ok(icp)
 }
 
 pub fn make_rot(km: &dyn KeyManager, state: IdentifierState) -> Result<EventMessage, Error> {
-    let key_prefix = vec![Basic::Ed25519.derive(km.public_key())];
-    let nxt_key_prefix = vec![Basic::Ed25519.derive(km.next_public_key())];
+    make_rot_with_seal(km, state, &[])
+}
+
+// Makes rotation event which anchors given seals in its data.
+pub fn make_rot_with_seal(
+    km: &dyn KeyManager,
+    state: IdentifierState,
+    seal_list: &[Seal],
+) -> Result<EventMessage, Error> {
+    let keys = vec![key_prefix(km.public_key())?];
+    let next_keys = vec![key_prefix(km.next_public_key())?];
     let ixn = EventMsgBuilder::new(EventType::Rotation)?
         .with_prefix(state.prefix.clone())
         .with_sn(state.sn + 1)
         .with_previous_event(SelfAddressing::Blake3_256.derive(&state.last))
-        .with_keys(key_prefix)
-        .with_next_keys(nxt_key_prefix)
+        .with_keys(keys)
+        .with_next_keys(next_keys)
+        .with_seal(seal_list.to_owned())
         .build()?;
     Ok(ixn)
 }
 
+pub fn make_rot_with_threshold(
+    kms: &[&dyn KeyManager],
+    threshold: &SignatureThreshold,
+    next_threshold: &SignatureThreshold,
+    state: IdentifierState,
+) -> Result<EventMessage, Error> {
+    let keys: Vec<_> = kms
+        .iter()
+        .map(|km| key_prefix(km.public_key()))
+        .collect::<Result<_, _>>()?;
+    let next_keys: Vec<_> = kms
+        .iter()
+        .map(|km| key_prefix(km.next_public_key()))
+        .collect::<Result<_, _>>()?;
+    validate_threshold(threshold, keys.len())?;
+    validate_threshold(next_threshold, next_keys.len())?;
+    let rot = EventMsgBuilder::new(EventType::Rotation)?
+        .with_prefix(state.prefix.clone())
+        .with_sn(state.sn + 1)
+        .with_previous_event(SelfAddressing::Blake3_256.derive(&state.last))
+        .with_keys(keys)
+        .with_threshold(threshold)
+        .with_next_keys(next_keys)
+        .with_next_threshold(next_threshold)
+        .build()?;
+    Ok(rot)
+}
+
+// Checks if threshold is satisfied by signatures of all `key_count` keys and
+// isn't satisfied without any signatures. For weighted threshold it means
+// that weights are proper fractions, one for each key, which sum to at
+// least 1.
+pub fn validate_threshold(threshold: &SignatureThreshold, key_count: usize) -> Result<(), Error> {
+    let all_keys: Vec<usize> = (0..key_count).collect();
+    let well_formed = threshold.enough_signatures(&all_keys).unwrap_or(false)
+        && !threshold.enough_signatures(&[]).unwrap_or(true);
+    if well_formed {
+        Ok(())
+    } else {
+        Err(Error::Generic(format!(
+            "Threshold can't be satisfied by {} keys",
+            key_count
+        )))
+    }
+}
+
+// Makes rotation event without next keys commitment, after which no more
+// establishment events are possible.
+pub fn make_abandon(km: &dyn KeyManager, state: IdentifierState) -> Result<EventMessage, Error> {
+    let mut rot = make_rot(km, state)?.event;
+    if let EventData::Rot(ref mut rot_data) = rot.event_data {
+        rot_data.key_config.threshold_key_digest = None;
+    }
+    Ok(rot.to_message(SerializationFormats::JSON)?)
+}
+
 pub fn make_ixn_with_seal(
     seal_list: &[Seal],
     state: IdentifierState,
@@ -1,34 +1,60 @@
 use std::{
     fmt::{self, Debug},
-    path::Path,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::RwLock,
 };
 
 // use event_generator::{Key, KeyType};
 use keri::{
     database::sled::SledEventDatabase,
-    derivation::{self_addressing::SelfAddressing, self_signing::SelfSigning},
+    derivation::{basic::Basic, self_addressing::SelfAddressing, self_signing::SelfSigning},
     event::{
         event_data::EventData,
-        sections::seal::{DigestSeal, Seal},
+        sections::{
+            seal::{DigestSeal, EventSeal, Seal},
+            threshold::SignatureThreshold,
+        },
         EventMessage,
     },
-    event_message::parse::signed_message,
-    event_message::parse::{message, signed_event_stream, Deserialized},
-    event_message::SignedEventMessage,
-    prefix::AttachedSignaturePrefix,
-    prefix::{IdentifierPrefix, SelfAddressingPrefix},
+    event_message::parse::{message, signed_event_stream, signed_message, Deserialized},
+    event_message::{SignedEventMessage, SignedTransferableReceipt},
+    keys::PublicKey,
+    prefix::{AttachedSignaturePrefix, BasicPrefix},
+    prefix::{IdentifierPrefix, Prefix, SelfAddressingPrefix},
     processor::EventProcessor,
     signer::KeyManager,
     state::IdentifierState,
 };
 use teliox::event::Event;
 
-use crate::error::Error;
+use crate::{controller::anchoring::AnchoringFailure, error::Error, tel::canonical_bytes};
+use log::{trace, warn};
 pub mod event_generator;
 
+// Parses serialized signed event message, so it can be passed to processor.
+pub(crate) fn parse_signed_message(data: &[u8]) -> Result<Deserialized, Error> {
+    signed_message(data)
+        .map(|(_rest, event)| event)
+        .map_err(|e| Error::Parse(e.to_string()))
+}
+
+// Parses serialized event message without signatures.
+pub(crate) fn parse_event_message(data: &[u8]) -> Result<EventMessage, Error> {
+    message(data)
+        .map(|(_rest, event)| event.event)
+        .map_err(|e| Error::Parse(e.to_string()))
+}
+
+// Keeps the prefix of local identifier inside kel database directory.
+const PREFIX_RECORD: &str = "identifier";
+
 pub struct KERL {
     prefix: IdentifierPrefix,
     database: SledEventDatabase,
+    path: PathBuf,
+    // State of local identifier, updated with every processed own event.
+    state: RwLock<Option<IdentifierState>>,
 }
 
 impl Debug for KERL {
@@ -51,22 +77,113 @@ impl<'d> KERL {
         Ok(KERL {
             prefix: IdentifierPrefix::default(),
             database: db,
+            path: path.to_owned(),
+            state: RwLock::new(None),
         })
     }
 
+    // Opens existing kel database and recovers local identifier prefix.
+    // Sled database doesn't allow listing stored identifiers, so prefix is
+    // read from the record saved on inception and checked against inception
+    // event stored in database.
+    pub fn from_db(path: &Path) -> Result<KERL, Error> {
+        let prefix: IdentifierPrefix = std::fs::read_to_string(path.join(PREFIX_RECORD))
+            .map_err(|_| Error::Generic("Missing inception event".into()))?
+            .trim()
+            .parse()?;
+        let kerl = KERL {
+            prefix,
+            database: KERL::create_kel_db(path)?,
+            path: path.to_owned(),
+            state: RwLock::new(None),
+        };
+        match kerl
+            .get_event_at_sn(&kerl.prefix, 0)?
+            .map(|ev| ev.event.event_data)
+        {
+            Some(EventData::Icp(_)) => Ok(kerl),
+            _ => Err(Error::Generic("Missing inception event".into())),
+        }
+    }
+
+    // Creates kel database from exported KEL of local identifier. All events
+    // are verified while processing.
+    pub fn restore(path: &Path, kel: &[u8]) -> Result<KERL, Error> {
+        let prefix = match signed_event_stream(kel)
+            .map_err(|e| Error::Parse(e.to_string()))?
+            .1
+            .first()
+        {
+            Some(Deserialized::Event(e)) => e.event.event.event.prefix.clone(),
+            _ => return Err(Error::Generic("KEL doesn't start with event".into())),
+        };
+        let mut kerl = KERL::new(path)?;
+        kerl.process_kel(kel)?;
+        match kerl
+            .get_event_at_sn(&prefix, 0)?
+            .map(|ev| ev.event.event_data)
+        {
+            Some(EventData::Icp(_)) => kerl.set_prefix(prefix)?,
+            _ => return Err(Error::Generic("Missing inception event".into())),
+        };
+        Ok(kerl)
+    }
+
+    // Checks if kel database in given directory contains incepted identifier.
+    pub fn is_incepted(path: &Path) -> bool {
+        path.join(PREFIX_RECORD).exists()
+    }
+
+    fn set_prefix(&mut self, prefix: IdentifierPrefix) -> Result<(), Error> {
+        std::fs::write(self.path.join(PREFIX_RECORD), prefix.to_str())?;
+        self.prefix = prefix;
+        Ok(())
+    }
+
+    // Processes event of local identifier and caches resulting state.
+    fn process_own(&self, event: &SignedEventMessage) -> Result<(), Error> {
+        let state = EventProcessor::new(&self.database)
+            .process(parse_signed_message(&event.serialize()?)?)?;
+        trace!(
+            "Processed own KEL event of {} at sn {}",
+            event.event_message.event.prefix.to_str(),
+            event.event_message.event.sn
+        );
+        *self.state.write().unwrap() = state;
+        Ok(())
+    }
+
+    // Drops cached state, so it will be recomputed from database. Should be
+    // called after processing events which didn't come from local
+    // identifier.
+    fn invalidate_state(&self) {
+        *self.state.write().unwrap() = None;
+    }
+
     fn create_kel_db(path: &Path) -> Result<SledEventDatabase, Error> {
-        SledEventDatabase::new(path).map_err(|e| e.into())
+        SledEventDatabase::new(path).map_err(|e| Error::database(path, e))
     }
 
     pub fn process(&self, msg: &[u8], signature: &[u8]) -> Result<SignedEventMessage, Error> {
-        let processor = EventProcessor::new(&self.database);
-        let message = message(&msg).unwrap().1.event;
+        let message = parse_event_message(&msg)?;
         let sigged = message.sign(vec![AttachedSignaturePrefix::new(
             SelfSigning::Ed25519Sha512,
             signature.to_vec(),
             0,
         )]);
-        processor.process(signed_message(&sigged.serialize()?).unwrap().1)?;
+        self.process_own(&sigged)?;
+
+        Ok(sigged)
+    }
+
+    // Processes own event signed with many keys.
+    pub fn process_with_signatures(
+        &self,
+        message: &EventMessage,
+        signatures: Vec<AttachedSignaturePrefix>,
+    ) -> Result<SignedEventMessage, Error> {
+        let sigged = message.sign(signatures);
+        self.process_own(&sigged)?;
 
         Ok(sigged)
     }
@@ -76,18 +193,17 @@ impl<'d> KERL {
         msg: Vec<u8>,
         signature: Vec<u8>,
     ) -> Result<SignedEventMessage, Error> {
-        let processor = EventProcessor::new(&self.database);
-        let message = message(&msg).unwrap().1.event;
+        let message = parse_event_message(&msg)?;
         let sigged = message.sign(vec![AttachedSignaturePrefix::new(
             SelfSigning::Ed25519Sha512,
             signature,
             0,
         )]);
-        processor.process(signed_message(&sigged.serialize()?).unwrap().1)?;
+        self.process_own(&sigged)?;
         match message.event.event_data {
             EventData::Icp(_) => {
                 if self.prefix == IdentifierPrefix::default() {
-                    self.prefix = message.clone().event.prefix
+                    self.set_prefix(message.clone().event.prefix)?
                 }
             }
             _ => {}
@@ -96,43 +212,132 @@ impl<'d> KERL {
     }
 
     pub fn incept<K: KeyManager>(&mut self, key_manager: &K) -> Result<SignedEventMessage, Error> {
-        let icp = event_generator::make_icp(key_manager, Some(self.prefix.clone())).unwrap();
+        let icp = event_generator::make_icp(key_manager, Some(self.prefix.clone()))?;
 
         let sigged = icp.sign(vec![AttachedSignaturePrefix::new(
-            SelfSigning::Ed25519Sha512,
+            signing_key(key_manager)?.1,
             key_manager.sign(&icp.serialize()?)?,
             0,
         )]);
 
-        let processor = EventProcessor::new(&self.database);
-        processor.process(signed_message(&sigged.serialize()?).unwrap().1)?;
+        self.process_own(&sigged)?;
+
+        self.set_prefix(icp.event.prefix)?;
+
+        Ok(sigged)
+    }
+
+    // Incepts identifier which designates `witnesses`, receipts of `tally`
+    // of them are required for verification of vcs anchored in its events.
+    pub fn incept_with_witnesses<K: KeyManager>(
+        &mut self,
+        key_manager: &K,
+        witnesses: &[BasicPrefix],
+        tally: u64,
+    ) -> Result<SignedEventMessage, Error> {
+        let icp = event_generator::make_icp_with_witnesses(key_manager, witnesses, tally)?;
+        let sigged = icp.sign(vec![AttachedSignaturePrefix::new(
+            signing_key(key_manager)?.1,
+            key_manager.sign(&icp.serialize()?)?,
+            0,
+        )]);
+
+        self.process_own(&sigged)?;
 
-        self.prefix = icp.event.prefix;
+        self.set_prefix(icp.event.prefix)?;
 
         Ok(sigged)
     }
 
+    // Incepts identifier with keys of all key managers, which sign the
+    // inception event with signatures indexed in their order.
+    pub fn incept_with_threshold(
+        &mut self,
+        key_managers: &[&dyn KeyManager],
+        threshold: &SignatureThreshold,
+        next_threshold: &SignatureThreshold,
+    ) -> Result<SignedEventMessage, Error> {
+        let icp =
+            event_generator::make_icp_with_threshold(key_managers, threshold, next_threshold)?;
+        let sigged = icp.sign(sign_by_all(key_managers, &icp.serialize()?)?);
+
+        self.process_own(&sigged)?;
+
+        self.set_prefix(icp.event.prefix)?;
+
+        Ok(sigged)
+    }
+
+    pub fn rotate_with_threshold(
+        &self,
+        key_managers: &[&dyn KeyManager],
+        threshold: &SignatureThreshold,
+        next_threshold: &SignatureThreshold,
+    ) -> Result<SignedEventMessage, Error> {
+        let rot = event_generator::make_rot_with_threshold(
+            key_managers,
+            threshold,
+            next_threshold,
+            self.active_state()?,
+        )?;
+        let rot = rot.sign(sign_by_all(key_managers, &rot.serialize()?)?);
+
+        self.process_own(&rot)?;
+
+        Ok(rot)
+    }
+
     pub fn rotate<K: KeyManager>(&self, key_manager: &K) -> Result<SignedEventMessage, Error> {
-        let rot = event_generator::make_rot(key_manager, self.get_state()?.unwrap()).unwrap();
+        self.rotate_with_seal(key_manager, &[])
+    }
+
+    // Rotates to current keys of `key_manager`, anchoring given seals in
+    // the rotation event.
+    pub fn rotate_with_seal<K: KeyManager>(
+        &self,
+        key_manager: &K,
+        seal_list: &[Seal],
+    ) -> Result<SignedEventMessage, Error> {
+        let rot =
+            event_generator::make_rot_with_seal(key_manager, self.active_state()?, seal_list)?;
 
         let rot = rot.sign(vec![AttachedSignaturePrefix::new(
-            SelfSigning::Ed25519Sha512,
+            signing_key(key_manager)?.1,
             key_manager.sign(&rot.serialize()?)?,
             0,
         )]);
 
-        let processor = EventProcessor::new(&self.database);
-        processor.process(signed_message(&rot.serialize()?).unwrap().1)?;
+        self.process_own(&rot)?;
 
         Ok(rot)
     }
 
+    // Rotates to keys of `key_manager` without committing to next keys.
+    // Identifier can't make any events afterwards.
+    pub fn abandon<K: KeyManager>(&self, key_manager: &K) -> Result<SignedEventMessage, Error> {
+        let rot = event_generator::make_abandon(key_manager, self.active_state()?)?;
+
+        let rot = rot.sign(vec![AttachedSignaturePrefix::new(
+            signing_key(key_manager)?.1,
+            key_manager.sign(&rot.serialize()?)?,
+            0,
+        )]);
+
+        self.process_own(&rot)?;
+
+        Ok(rot)
+    }
+
+    pub fn is_abandoned(&self) -> Result<bool, Error> {
+        Ok(self.current_state()?.current.threshold_key_digest.is_none())
+    }
+
     pub fn make_ixn<K: KeyManager>(
         &mut self,
         payload: Option<&str>,
         key_manager: &K,
     ) -> Result<SignedEventMessage, Error> {
-        let state = self.get_state()?.unwrap();
+        let state = self.active_state()?;
         let seal_list = match payload {
             Some(payload) => {
                 vec![Seal::Digest(DigestSeal {
@@ -142,45 +347,53 @@ impl<'d> KERL {
             None => vec![],
         };
 
-        let ev = event_generator::make_ixn_with_seal(&seal_list, state).unwrap();
+        let ev = event_generator::make_ixn_with_seal(&seal_list, state)?;
 
         let ixn = ev.sign(vec![AttachedSignaturePrefix::new(
-            SelfSigning::Ed25519Sha512,
+            signing_key(key_manager)?.1,
             key_manager.sign(&ev.serialize()?)?,
             0,
         )]);
 
-        let processor = EventProcessor::new(&self.database);
-        processor.process(signed_message(&ixn.serialize()?).unwrap().1)?;
+        self.process_own(&ixn)?;
 
         Ok(ixn)
     }
 
+    // Anchors already computed digest in interaction event, without hashing
+    // it again.
+    pub fn anchor_digest<K: KeyManager>(
+        &self,
+        digest: SelfAddressingPrefix,
+        key_manager: &K,
+    ) -> Result<SignedEventMessage, Error> {
+        self.make_ixn_with_seal(&[Seal::Digest(DigestSeal { dig: digest })], key_manager)
+    }
+
     pub fn make_ixn_with_seal<K: KeyManager>(
         &self,
         seal_list: &[Seal],
         key_manager: &K,
     ) -> Result<SignedEventMessage, Error> {
-        let state = self.get_state()?.unwrap();
+        let state = self.active_state()?;
 
-        let ev = event_generator::make_ixn_with_seal(seal_list, state).unwrap();
+        let ev = event_generator::make_ixn_with_seal(seal_list, state)?;
 
         let ixn = ev.sign(vec![AttachedSignaturePrefix::new(
-            SelfSigning::Ed25519Sha512,
+            signing_key(key_manager)?.1,
             key_manager.sign(&ev.serialize()?)?,
             0,
         )]);
 
-        let processor = EventProcessor::new(&self.database);
-        processor.process(signed_message(&ixn.serialize()?).unwrap().1)?;
+        self.process_own(&ixn)?;
 
         Ok(ixn)
     }
 
     pub fn make_ixn_seal(&self, seal_list: &[Seal]) -> Result<EventMessage, Error> {
-        let state = self.get_state()?.unwrap();
+        let state = self.active_state()?;
 
-        let ev = event_generator::make_ixn_with_seal(seal_list, state).unwrap();
+        let ev = event_generator::make_ixn_with_seal(seal_list, state)?;
 
         Ok(ev)
     }
@@ -188,12 +401,20 @@ impl<'d> KERL {
     pub fn respond<K: KeyManager>(&self, msg: &[u8], key_manager: &K) -> Result<Vec<u8>, Error> {
         let processor = EventProcessor::new(&self.database);
         let events = signed_event_stream(msg)
-            .map_err(|e| Error::Generic(e.to_string()))?
+            .map_err(|e| Error::Parse(e.to_string()))?
             .1;
         let (processed_ok, _processed_failed): (Vec<_>, Vec<_>) = events
             .into_iter()
             .map(|event| processor.process(event.clone()).and_then(|_| Ok(event)))
             .partition(Result::is_ok);
+        self.invalidate_state();
+        // Own KEL is attached to receipt of inception event, unless local
+        // identifier isn't incepted yet.
+        let own_kel = if self.prefix == IdentifierPrefix::default() {
+            None
+        } else {
+            processor.get_kerl(&self.prefix)?
+        };
         let response: Vec<u8> = processed_ok
             .into_iter()
             .map(Result::unwrap)
@@ -201,17 +422,15 @@ impl<'d> KERL {
                 match des_event {
                     Deserialized::Event(ev) => {
                         let mut buf = vec![];
-                        if let EventData::Icp(_) = ev.event.event.event.event_data {
+                        if let (EventData::Icp(_), Some(own_kel)) =
+                            (&ev.event.event.event.event_data, &own_kel)
+                        {
                             if !processor.has_receipt(
                                 &self.prefix,
                                 0,
                                 &ev.event.event.event.prefix,
                             )? {
-                                buf.append(
-                                    &mut processor
-                                        .get_kerl(&self.prefix)?
-                                        .ok_or(Error::Generic("KEL is empty".into()))?,
-                                )
+                                buf.extend_from_slice(own_kel)
                             }
                         }
                         buf.append(
@@ -234,7 +453,7 @@ impl<'d> KERL {
         &self,
         event: EventMessage,
         key_manager: &K,
-    ) -> Result<SignedEventMessage, Error> {
+    ) -> Result<SignedTransferableReceipt, Error> {
         let ser = event.serialize()?;
         let signature = key_manager.sign(&ser)?;
         let processor = EventProcessor::new(&self.database);
@@ -244,27 +463,195 @@ impl<'d> KERL {
             .ok_or(Error::Generic("No establishment event seal".into()))?;
 
         let rcp =
-            event_generator::make_rct(event, validator_event_seal, self.get_state()?.unwrap())
-                .unwrap();
+            event_generator::make_rct(event, validator_event_seal.clone(), self.current_state()?)?;
 
-        let rcp = rcp.sign(vec![AttachedSignaturePrefix::new(
-            SelfSigning::Ed25519Sha512,
-            signature,
-            0,
-        )]);
-        processor.process(signed_message(&rcp.serialize()?).unwrap().1)?;
+        let rcp = SignedTransferableReceipt::new(
+            &rcp,
+            validator_event_seal,
+            vec![AttachedSignaturePrefix::new(
+                signing_key(key_manager)?.1,
+                signature,
+                0,
+            )],
+        );
+        processor.process(parse_signed_message(&rcp.serialize()?)?)?;
 
         Ok(rcp)
     }
 
+    // Processes receipts of events (possibly along with receipting
+    // identifiers' KELs), so they can be counted by `receipt_count`.
+    pub fn add_receipt(&mut self, receipt_stream: &[u8]) -> Result<(), Error> {
+        self.process_kel(receipt_stream)
+    }
+
+    // Processes all events from stream. Fails on first event which can't be
+    // processed.
+    pub fn process_kel(&self, stream: &[u8]) -> Result<(), Error> {
+        let processor = EventProcessor::new(&self.database);
+        let result = signed_event_stream(stream)
+            .map_err(|e| Error::Parse(e.to_string()))?
+            .1
+            .into_iter()
+            .try_for_each(|event| processor.process(event).map(|_| ()));
+        self.invalidate_state();
+        if let Err(e) = &result {
+            warn!("Rejected KEL event: {}", e);
+        }
+        result?;
+        Ok(())
+    }
+
+    // Processes all events from stream, like `respond`, but doesn't make
+    // any receipts, so it needs no key manager. Meant for verifiers, which
+    // shouldn't sign anything. Events which can't be processed are reported,
+    // after processing the valid ones.
+    pub fn process_stream(&self, stream: &[u8]) -> Result<ProcessReport, Error> {
+        let processor = EventProcessor::new(&self.database);
+        let mut report = ProcessReport::default();
+        for (i, event) in signed_event_stream(stream)
+            .map_err(|e| Error::Parse(e.to_string()))?
+            .1
+            .into_iter()
+            .enumerate()
+        {
+            match processor.process(event) {
+                Ok(_) => {
+                    trace!("Processed KEL event {} of stream", i);
+                    report.processed += 1
+                }
+                Err(e) => {
+                    warn!("Rejected KEL event {} of stream: {}", i, e);
+                    report.rejected.push(format!("event {}: {}", i, e))
+                }
+            }
+        }
+        self.invalidate_state();
+        Ok(report)
+    }
+
+    // Returns number of distinct identifiers which receipted event of
+    // `prefix` at `sn`.
+    pub fn receipt_count(&self, prefix: &IdentifierPrefix, sn: u64) -> Result<usize, Error> {
+        Ok(self.get_receipt_validators(prefix, sn)?.len())
+    }
+
+    // Returns distinct identifiers which receipted event of `prefix` at
+    // `sn`, transferable validators first.
+    pub fn get_receipt_validators(
+        &self,
+        prefix: &IdentifierPrefix,
+        sn: u64,
+    ) -> Result<Vec<IdentifierPrefix>, Error> {
+        let mut validators: Vec<IdentifierPrefix> = vec![];
+        if let Some(receipts) = self.database.get_receipts_t(prefix) {
+            receipts
+                .filter(|rct| rct.body.event.sn == sn)
+                .map(|rct| rct.validator_seal.event_seal.prefix)
+                .for_each(|validator| {
+                    if !validators.contains(&validator) {
+                        validators.push(validator)
+                    }
+                });
+        }
+        if let Some(receipts) = self.database.get_receipts_nt(prefix) {
+            receipts
+                .filter(|rct| rct.body.event.sn == sn)
+                .flat_map(|rct| rct.couplets.into_iter())
+                .map(|(witness, _signature)| IdentifierPrefix::Basic(witness))
+                .for_each(|validator| {
+                    if !validators.contains(&validator) {
+                        validators.push(validator)
+                    }
+                });
+        }
+        Ok(validators)
+    }
+
+    // Returns number of given witnesses which receipted event of `prefix` at
+    // `sn`. Receipts of other validators aren't counted.
+    pub fn witness_receipt_count(
+        &self,
+        prefix: &IdentifierPrefix,
+        sn: u64,
+        witnesses: &[BasicPrefix],
+    ) -> Result<usize, Error> {
+        Ok(self
+            .get_receipt_validators(prefix, sn)?
+            .into_iter()
+            .filter(|validator| match validator {
+                IdentifierPrefix::Basic(basic) => witnesses.contains(basic),
+                _ => false,
+            })
+            .count())
+    }
+
+    // Returns transferable receipts of event of `prefix` at `sn`.
+    pub fn get_transferable_receipts(
+        &self,
+        prefix: &IdentifierPrefix,
+        sn: u64,
+    ) -> Result<Vec<SignedTransferableReceipt>, Error> {
+        Ok(self
+            .database
+            .get_receipts_t(prefix)
+            .map(|receipts| receipts.filter(|rct| rct.body.event.sn == sn).collect())
+            .unwrap_or_default())
+    }
+
+    // Checks if event of `prefix` at `sn` has at least as many witness
+    // receipts as witness threshold of the identifier requires. Receipts of
+    // validators which aren't witnesses don't count.
+    pub fn is_receipted(&self, prefix: &IdentifierPrefix, sn: u64) -> Result<bool, Error> {
+        let state = self
+            .get_state_for_prefix(prefix)?
+            .ok_or(Error::Generic("Unknown identifier".into()))?;
+        Ok(self.witness_receipt_count(prefix, sn, &state.witnesses)? as u64 >= state.tally)
+    }
+
     pub fn get_prefix(&self) -> IdentifierPrefix {
         self.prefix.clone()
     }
 
+    // Returns state of local identifier or error if it wasn't incepted yet.
+    fn current_state(&self) -> Result<IdentifierState, Error> {
+        self.get_state()?
+            .ok_or(Error::Generic("Identifier is not incepted".into()))
+    }
+
+    // Returns current state of local identifier, which can still make events.
+    fn active_state(&self) -> Result<IdentifierState, Error> {
+        let state = self.current_state()?;
+        if state.current.threshold_key_digest.is_none() {
+            return Err(Error::Abandoned);
+        }
+        Ok(state)
+    }
+
+    // Returns cached state of local identifier. It is computed from database
+    // if cache is empty.
     pub fn get_state(&self) -> Result<Option<IdentifierState>, Error> {
-        EventProcessor::new(&self.database)
+        if let Some(state) = self.state.read().unwrap().as_ref() {
+            if state.prefix == self.prefix {
+                return Ok(Some(state.clone()));
+            }
+        }
+        let state = EventProcessor::new(&self.database)
             .compute_state(&self.prefix)
-            .map_err(|e| Error::KeriError(e))
+            .map_err(|e| Error::KeriError(e))?;
+        *self.state.write().unwrap() = state.clone();
+        Ok(state)
+    }
+
+    // Returns event of identifier at `sn` with its signatures.
+    pub fn get_signed_event_at_sn(
+        &self,
+        id: &IdentifierPrefix,
+        sn: u64,
+    ) -> Result<Option<SignedEventMessage>, Error> {
+        Ok(EventProcessor::new(&self.database)
+            .get_event_at_sn(id, sn)?
+            .map(|e| e.event))
     }
 
     pub fn get_event_at_sn(
@@ -277,12 +664,83 @@ impl<'d> KERL {
             .map(|e| e.event.event_message))
     }
 
+    // Returns sn of last establishment event of identifier at or before `sn`.
+    pub fn get_establishment_sn(&self, id: &IdentifierPrefix, sn: u64) -> Result<u64, Error> {
+        for sn in (0..=sn).rev() {
+            let event = self
+                .get_event_at_sn(id, sn)?
+                .ok_or(Error::Generic(format!("Missing event at sn {}", sn)))?;
+            if matches!(
+                event.event.event_data,
+                EventData::Icp(_) | EventData::Rot(_)
+            ) {
+                return Ok(sn);
+            }
+        }
+        Err(Error::Generic("Missing establishment event".into()))
+    }
+
     pub fn get_kerl(&self) -> Result<Option<Vec<u8>>, Error> {
         EventProcessor::new(&self.database)
             .get_kerl(&self.prefix)
             .map_err(|e| Error::KeriError(e))
     }
 
+    // Returns serialized events of local KEL with sn greater than
+    // `after_sn`, each followed by its receipts, so replica holding KEL up to
+    // `after_sn` can catch up. Stream is empty if replica is current, or if
+    // there is no sn greater than `after_sn`.
+    pub fn get_kerl_since(&self, after_sn: u64) -> Result<Vec<u8>, Error> {
+        let mut stream = vec![];
+        let mut next_sn = after_sn.checked_add(1);
+        while let Some(sn) = next_sn {
+            let event = match self.get_signed_event_at_sn(&self.prefix, sn)? {
+                Some(event) => event,
+                None => break,
+            };
+            stream.extend(event.serialize()?);
+            for receipt in self.get_transferable_receipts(&self.prefix, sn)? {
+                stream.extend(receipt.serialize()?);
+            }
+            if let Some(receipts) = self.database.get_receipts_nt(&self.prefix) {
+                for receipt in receipts.filter(|rct| rct.body.event.sn == sn) {
+                    stream.extend(receipt.serialize()?);
+                }
+            }
+            next_sn = sn.checked_add(1);
+        }
+        Ok(stream)
+    }
+
+    // Returns kinds of events of local KEL, in order.
+    pub fn event_kinds(&self) -> Result<Vec<EventKind>, Error> {
+        match self.get_kerl()? {
+            Some(kel) => event_kinds(&kel),
+            None => Ok(vec![]),
+        }
+    }
+
+    // Writes serialized events of local KEL one by one into the writer.
+    pub fn write_kerl<W: Write>(&self, w: &mut W) -> Result<(), Error> {
+        match self.get_state()? {
+            Some(state) => self.write_kerl_to_sn(state.sn, w),
+            None => Ok(()),
+        }
+    }
+
+    // Writes serialized events of local KEL up to `last_sn` (inclusive) into
+    // the writer.
+    pub fn write_kerl_to_sn<W: Write>(&self, last_sn: u64, w: &mut W) -> Result<(), Error> {
+        let processor = EventProcessor::new(&self.database);
+        for sn in 0..=last_sn {
+            let event = processor
+                .get_event_at_sn(&self.prefix, sn)?
+                .ok_or(Error::Generic(format!("Missing event at sn {}", sn)))?;
+            w.write_all(&event.event.serialize()?)?;
+        }
+        Ok(())
+    }
+
     pub fn get_state_for_prefix(
         &self,
         prefix: &IdentifierPrefix,
@@ -292,6 +750,16 @@ impl<'d> KERL {
             .map_err(|e| Error::KeriError(e))
     }
 
+    pub fn get_state_at_sn(
+        &self,
+        prefix: &IdentifierPrefix,
+        sn: u64,
+    ) -> Result<Option<IdentifierState>, Error> {
+        EventProcessor::new(&self.database)
+            .compute_state_at_sn(prefix, sn)
+            .map_err(|e| Error::KeriError(e))
+    }
+
     pub fn get_state_for_seal(
         &self,
         prefix: &IdentifierPrefix,
@@ -304,7 +772,7 @@ impl<'d> KERL {
         {
             Some(s) => {
                 if !digest.verify_binding(&s.last) {
-                    Err(Error::Generic("Last event digests doesn't match".into()))
+                    Err(AnchoringFailure::SourceSealMismatch(sn).into())
                 } else {
                     Ok(Some(s))
                 }
@@ -313,29 +781,547 @@ impl<'d> KERL {
         }
     }
 
+    // Checks if digest of source seal matches event of issuer's KEL at `sn`,
+    // so seal pointing to the right sn with wrong digest is rejected.
+    pub fn check_source_seal(
+        &self,
+        sn: u64,
+        issuer_id: &IdentifierPrefix,
+        digest: &SelfAddressingPrefix,
+    ) -> Result<bool, Error> {
+        Ok(match self.get_event_at_sn(issuer_id, sn)? {
+            Some(event) => digest.verify_binding(&event.serialize()?),
+            None => false,
+        })
+    }
+
     // Checks if event from issuers kel has event seal of tel event in its data field.
+    // Digest of seal is checked with derivation encoded in the seal, so seals
+    // made with any derivation are accepted.
     pub fn check_seal(
         &self,
         sn: u64,
         issuer_id: &IdentifierPrefix,
         tel_ev: &Event,
     ) -> Result<bool, Error> {
-        let event = self.get_event_at_sn(issuer_id, sn)?;
-        let data = tel_ev.serialize()?;
-        Ok(match event.unwrap().event.event_data {
-            EventData::Icp(icp) => Ok(icp.data),
-            EventData::Rot(rot) => Ok(rot.data),
-            EventData::Ixn(ixn) => Ok(ixn.data),
-            _ => Err(Error::Generic("Empty data".into())),
-        }?
-        .iter()
-        .any(|seal| match seal {
-            Seal::Event(es) => {
-                es.prefix == tel_ev.get_prefix()
-                    && es.sn == tel_ev.get_sn()
-                    && es.event_digest.verify_binding(&data)
+        let event = self
+            .get_event_at_sn(issuer_id, sn)?
+            .ok_or(Error::Generic("Missing anchoring event".into()))?;
+        contains_seal(&event, tel_ev)
+    }
+}
+
+// Result of processing KEL stream with `process_stream`.
+#[derive(Debug, Default)]
+pub struct ProcessReport {
+    pub processed: usize,
+    // Reasons of rejection of events which couldn't be processed, with their
+    // positions in stream.
+    pub rejected: Vec<String>,
+}
+
+// Kind of event in KEL stream.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+    Icp,
+    // Delegated rotations are reported as `Rot` too.
+    Rot,
+    Ixn,
+    Dip,
+    Receipt,
+}
+
+// Returns kinds of events in serialized KEL, in order.
+pub fn event_kinds(stream: &[u8]) -> Result<Vec<EventKind>, Error> {
+    Ok(signed_event_stream(stream)
+        .map_err(|e| Error::Parse(e.to_string()))?
+        .1
+        .into_iter()
+        .map(|event| match event {
+            Deserialized::Event(e) => match e.event.event.event.event_data {
+                EventData::Icp(_) => EventKind::Icp,
+                EventData::Rot(_) | EventData::Drt(_) => EventKind::Rot,
+                EventData::Ixn(_) => EventKind::Ixn,
+                EventData::Dip(_) => EventKind::Dip,
+                EventData::Rct(_) => EventKind::Receipt,
+            },
+            Deserialized::NontransferableRct(_) | Deserialized::TransferableRct(_) => {
+                EventKind::Receipt
             }
-            _ => false,
-        }))
+        })
+        .collect())
+}
+
+// Checks if KEL event contains event seal of tel event.
+pub fn contains_seal(event: &EventMessage, tel_ev: &Event) -> Result<bool, Error> {
+    Ok(find_seal(event, tel_ev)?.is_some())
+}
+
+// Returns position of event seal of tel event in data of KEL event. Tel
+// event can be sealed only once, so more than one seal of the same tel
+// prefix and sn is an error, even if digests differ.
+pub fn find_seal(event: &EventMessage, tel_ev: &Event) -> Result<Option<usize>, Error> {
+    let data = canonical_bytes(tel_ev)?;
+    let seals: Vec<(usize, &EventSeal)> = match &event.event.event_data {
+        EventData::Icp(icp) => Ok(&icp.data),
+        EventData::Rot(rot) => Ok(&rot.data),
+        EventData::Ixn(ixn) => Ok(&ixn.data),
+        _ => Err(Error::Generic("Empty data".into())),
+    }?
+    .iter()
+    .enumerate()
+    .filter_map(|(index, seal)| match seal {
+        Seal::Event(es) if es.prefix == tel_ev.get_prefix() && es.sn == tel_ev.get_sn() => {
+            Some((index, es))
+        }
+        _ => None,
+    })
+    .collect();
+    match seals.as_slice() {
+        [] => Ok(None),
+        [(index, es)] => Ok(Some(*index).filter(|_| es.event_digest.verify_binding(&data))),
+        _ => Err(AnchoringFailure::DuplicateEventSeal(event.event.sn).into()),
     }
 }
+
+// Returns code of signatures made with given key.
+pub fn signature_code(key: &BasicPrefix) -> Result<SelfSigning, Error> {
+    match key.derivation {
+        Basic::Ed25519 | Basic::Ed25519NT => Ok(SelfSigning::Ed25519Sha512),
+        Basic::ECDSAsecp256k1 | Basic::ECDSAsecp256k1NT => Ok(SelfSigning::ECDSAsecp256k1Sha256),
+        Basic::Ed448 | Basic::Ed448NT => Ok(SelfSigning::Ed448),
+        _ => Err(Error::Generic(format!(
+            "Key {} can't be used for signing",
+            key.to_str()
+        ))),
+    }
+}
+
+// Derives prefix of public key of key manager. Key manager doesn't tell type
+// of its keys, so it's recognized by key length: 32 bytes for Ed25519 and 33
+// bytes of compressed point for secp256k1.
+pub fn key_prefix(public_key: PublicKey) -> Result<BasicPrefix, Error> {
+    match public_key.key().len() {
+        32 => Ok(Basic::Ed25519.derive(public_key)),
+        33 => Ok(Basic::ECDSAsecp256k1.derive(public_key)),
+        len => Err(Error::Generic(format!(
+            "Unsupported public key length: {}",
+            len
+        ))),
+    }
+}
+
+// Returns prefix of current key of key manager, with code of signatures
+// made with it.
+pub fn signing_key<K: KeyManager + ?Sized>(km: &K) -> Result<(BasicPrefix, SelfSigning), Error> {
+    let key = key_prefix(km.public_key())?;
+    let code = signature_code(&key)?;
+    Ok((key, code))
+}
+
+// Signs message with every key manager, using its position as signature
+// index.
+fn sign_by_all(
+    key_managers: &[&dyn KeyManager],
+    message: &Vec<u8>,
+) -> Result<Vec<AttachedSignaturePrefix>, Error> {
+    key_managers
+        .iter()
+        .enumerate()
+        .map(|(i, km)| {
+            Ok(AttachedSignaturePrefix::new(
+                signing_key(*km)?.1,
+                km.sign(message)?,
+                i as u16,
+            ))
+        })
+        .collect()
+}
+
+// Checks if signatures which verify against keys at their indexes satisfy
+// the signature threshold of given state, either simple or weighted.
+pub fn verify_signatures(
+    state: &IdentifierState,
+    message: &[u8],
+    signatures: &[AttachedSignaturePrefix],
+) -> Result<bool, Error> {
+    Ok(enough_signatures(
+        state,
+        &matched_indexes(state, message, signatures),
+    ))
+}
+
+// Returns sorted indexes of keys of given state, against which signatures
+// verify. Malformed signatures are treated as invalid ones.
+pub fn matched_indexes(
+    state: &IdentifierState,
+    message: &[u8],
+    signatures: &[AttachedSignaturePrefix],
+) -> Vec<usize> {
+    let mut valid_indexes: Vec<usize> = signatures
+        .iter()
+        .filter(|sig| {
+            state
+                .current
+                .public_keys
+                .get(sig.index as usize)
+                .map(|key| key.verify(message, &sig.signature).unwrap_or(false))
+                .unwrap_or(false)
+        })
+        .map(|sig| sig.index as usize)
+        .collect();
+    valid_indexes.sort_unstable();
+    valid_indexes.dedup();
+    valid_indexes
+}
+
+// Checks if keys at given indexes satisfy the signature threshold of state.
+pub fn enough_signatures(state: &IdentifierState, indexes: &[usize]) -> bool {
+    state
+        .current
+        .threshold
+        .enough_signatures(indexes)
+        .unwrap_or(false)
+}
+
+#[test]
+pub fn test_parse_corrupted_message() {
+    let corrupted =
+        br#"{"v":"KERI10JSON0000e6_","i":"DWzwEHHzq7K0gzQPYGGwTmuupUhPx5_yZ-Wk1x4ejhc","s":"#;
+    assert!(matches!(
+        parse_signed_message(corrupted),
+        Err(Error::Parse(_))
+    ));
+    assert!(matches!(
+        parse_event_message(&[0xff, 0x00, 0x13, 0x37]),
+        Err(Error::Parse(_))
+    ));
+}
+
+#[test]
+pub fn test_from_db() -> Result<(), Error> {
+    use keri::signer::CryptoBox;
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let prefix = {
+        let mut kerl = KERL::new(dir.path())?;
+        kerl.incept(&CryptoBox::new()?)?;
+        kerl.get_prefix()
+    };
+    let kerl = KERL::from_db(dir.path())?;
+    assert_eq!(kerl.get_prefix(), prefix);
+    assert!(kerl.get_state()?.is_some());
+
+    // Database without inception event.
+    let empty_dir = tempdir().unwrap();
+    {
+        KERL::new(empty_dir.path())?;
+    }
+    assert!(KERL::from_db(empty_dir.path()).is_err());
+
+    Ok(())
+}
+
+#[test]
+pub fn test_receipt_count() -> Result<(), Error> {
+    use keri::signer::CryptoBox;
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let mut kerl = KERL::new(dir.path())?;
+    kerl.incept(&CryptoBox::new()?)?;
+    let kel = kerl.get_kerl()?.unwrap();
+    assert_eq!(kerl.receipt_count(&kerl.get_prefix(), 0)?, 0);
+
+    for expected_count in 1..=2 {
+        let validator_dir = tempdir().unwrap();
+        let validator_km = CryptoBox::new()?;
+        let mut validator = KERL::new(validator_dir.path())?;
+        validator.incept(&validator_km)?;
+
+        // Response contains validator's KEL and receipt of inception event.
+        let receipt = validator.respond(&kel, &validator_km)?;
+        kerl.add_receipt(&receipt)?;
+        assert_eq!(kerl.receipt_count(&kerl.get_prefix(), 0)?, expected_count);
+    }
+    assert!(kerl.is_receipted(&kerl.get_prefix(), 0)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_is_receipted_by_witnesses() -> Result<(), Error> {
+    use keri::signer::CryptoBox;
+    use tempfile::tempdir;
+
+    let new_validator = || -> Result<_, Error> {
+        let dir = tempdir().unwrap();
+        let km = CryptoBox::new()?;
+        let mut validator = KERL::new(dir.path())?;
+        validator.incept(&km)?;
+        Ok((dir, km, validator))
+    };
+    let (_witness_dir, witness_km, witness) = new_validator()?;
+    let (_other_dir, other_km, other) = new_validator()?;
+    let witness_prefix = match witness.get_prefix() {
+        IdentifierPrefix::Basic(basic) => basic,
+        _ => unreachable!(),
+    };
+
+    let dir = tempdir().unwrap();
+    let mut kerl = KERL::new(dir.path())?;
+    kerl.incept_with_witnesses(&CryptoBox::new()?, &[witness_prefix], 1)?;
+    let kel = kerl.get_kerl()?.unwrap();
+    assert!(!kerl.is_receipted(&kerl.get_prefix(), 0)?);
+
+    // Receipt of validator which isn't a witness doesn't count.
+    kerl.add_receipt(&other.respond(&kel, &other_km)?)?;
+    assert_eq!(kerl.receipt_count(&kerl.get_prefix(), 0)?, 1);
+    assert!(!kerl.is_receipted(&kerl.get_prefix(), 0)?);
+
+    kerl.add_receipt(&witness.respond(&kel, &witness_km)?)?;
+    assert!(kerl.is_receipted(&kerl.get_prefix(), 0)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_get_kerl_since() -> Result<(), Error> {
+    use keri::signer::CryptoBox;
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let mut km = CryptoBox::new()?;
+    let mut kerl = KERL::new(dir.path())?;
+    kerl.incept(&km)?;
+    let replica_dir = tempdir().unwrap();
+    let replica = KERL::new(replica_dir.path())?;
+    replica.process_kel(&kerl.get_kerl()?.unwrap())?;
+
+    kerl.make_ixn(None, &km)?;
+    km.rotate()?;
+    kerl.rotate(&km)?;
+    kerl.make_ixn(None, &km)?;
+
+    // Validator receipts all events of the KEL.
+    let validator_dir = tempdir().unwrap();
+    let validator_km = CryptoBox::new()?;
+    let mut validator = KERL::new(validator_dir.path())?;
+    validator.incept(&validator_km)?;
+    kerl.add_receipt(&validator.respond(&kerl.get_kerl()?.unwrap(), &validator_km)?)?;
+    replica.process_kel(&validator.get_kerl()?.unwrap())?;
+
+    replica.process_kel(&kerl.get_kerl_since(0)?)?;
+    let (state, replica_state) = (
+        kerl.get_state()?.unwrap(),
+        replica.get_state_for_prefix(&kerl.get_prefix())?.unwrap(),
+    );
+    assert_eq!(replica_state.sn, 3);
+    assert_eq!(replica_state.sn, state.sn);
+    assert_eq!(replica_state.last, state.last);
+    assert_eq!(replica_state.current.public_keys, state.current.public_keys);
+    // Receipts of events after inception were delivered.
+    assert_eq!(replica.receipt_count(&kerl.get_prefix(), 0)?, 0);
+    assert_eq!(replica.receipt_count(&kerl.get_prefix(), 3)?, 1);
+
+    assert!(kerl.get_kerl_since(3)?.is_empty());
+    assert!(kerl.get_kerl_since(u64::MAX)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+pub fn test_respond() -> Result<(), Error> {
+    use keri::signer::CryptoBox;
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let mut kerl = KERL::new(dir.path())?;
+    kerl.incept(&CryptoBox::new()?)?;
+    let kel = kerl.get_kerl()?.unwrap();
+
+    // Validator without any receipts attaches its KEL to the receipt.
+    let validator_dir = tempdir().unwrap();
+    let validator_km = CryptoBox::new()?;
+    let mut validator = KERL::new(validator_dir.path())?;
+    validator.incept(&validator_km)?;
+    let response = validator.respond(&kel, &validator_km)?;
+    assert_eq!(
+        event_kinds(&response)?,
+        vec![EventKind::Icp, EventKind::Receipt]
+    );
+
+    // Not incepted validator still processes events, but has nothing to
+    // attach and can't make receipts.
+    let empty_dir = tempdir().unwrap();
+    let empty = KERL::new(empty_dir.path())?;
+    assert!(empty.respond(&kel, &CryptoBox::new()?)?.is_empty());
+    assert!(empty.get_state_for_prefix(&kerl.get_prefix())?.is_some());
+
+    Ok(())
+}
+
+#[test]
+pub fn test_process_stream() -> Result<(), Error> {
+    use keri::signer::CryptoBox;
+    use tempfile::tempdir;
+
+    let issuer_dir = tempdir().unwrap();
+    let km = CryptoBox::new()?;
+    let mut issuer = KERL::new(issuer_dir.path())?;
+    issuer.incept(&km)?;
+    issuer.make_ixn(Some("data"), &km)?;
+    let mut stream = issuer.get_kerl()?.unwrap();
+
+    // Event of other identifier, without its inception.
+    let other_dir = tempdir().unwrap();
+    let other_km = CryptoBox::new()?;
+    let mut other = KERL::new(other_dir.path())?;
+    other.incept(&other_km)?;
+    stream.extend(other.make_ixn(None, &other_km)?.serialize()?);
+
+    // Verifier has no identifier and no key manager.
+    let verifier_dir = tempdir().unwrap();
+    let verifier = KERL::new(verifier_dir.path())?;
+    let report = verifier.process_stream(&stream)?;
+    assert_eq!(report.processed, 2);
+    assert_eq!(report.rejected.len(), 1);
+    assert_eq!(
+        verifier
+            .get_state_for_prefix(&issuer.get_prefix())?
+            .unwrap()
+            .sn,
+        1
+    );
+    assert!(verifier
+        .get_state_for_prefix(&other.get_prefix())?
+        .is_none());
+    // No receipts were made.
+    assert_eq!(verifier.receipt_count(&issuer.get_prefix(), 0)?, 0);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_state_cache() -> Result<(), Error> {
+    use keri::signer::CryptoBox;
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let km = CryptoBox::new()?;
+    let mut kerl = KERL::new(dir.path())?;
+    kerl.incept(&km)?;
+    for i in 0..20 {
+        kerl.make_ixn(Some(i.to_string().as_str()), &km)?;
+        let cached = kerl.get_state()?.unwrap();
+        let recomputed = EventProcessor::new(&kerl.database)
+            .compute_state(&kerl.prefix)?
+            .unwrap();
+        assert_eq!(cached.sn, recomputed.sn);
+        assert_eq!(cached.last, recomputed.last);
+        assert_eq!(cached.current.public_keys, recomputed.current.public_keys);
+    }
+    assert_eq!(kerl.get_state()?.unwrap().sn, 20);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_weighted_threshold() -> Result<(), Error> {
+    use keri::signer::CryptoBox;
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let mut kms = vec![CryptoBox::new()?, CryptoBox::new()?, CryptoBox::new()?];
+    let two_of_three = SignatureThreshold::single_weighted(vec![(1, 2), (1, 2), (1, 2)]);
+
+    let mut kerl = KERL::new(dir.path())?;
+    {
+        // Weights which never sum to 1 are rejected.
+        let too_low = SignatureThreshold::single_weighted(vec![(1, 4), (1, 4), (1, 4)]);
+        let refs: Vec<&dyn KeyManager> = kms.iter().map(|km| km as &dyn KeyManager).collect();
+        assert!(kerl
+            .incept_with_threshold(&refs, &too_low, &two_of_three)
+            .is_err());
+        kerl.incept_with_threshold(&refs, &two_of_three, &two_of_three)?;
+    }
+
+    let message = b"message".to_vec();
+    let signatures: Vec<_> = kms
+        .iter()
+        .enumerate()
+        .map(|(i, km)| {
+            AttachedSignaturePrefix::new(
+                SelfSigning::Ed25519Sha512,
+                km.sign(&message).unwrap(),
+                i as u16,
+            )
+        })
+        .collect();
+    let state = kerl.get_state()?.unwrap();
+    assert!(!verify_signatures(&state, &message, &signatures[..1])?);
+    assert!(verify_signatures(&state, &message, &signatures[..2])?);
+    assert!(verify_signatures(&state, &message, &signatures[1..])?);
+
+    for km in kms.iter_mut() {
+        km.rotate()?;
+    }
+    let refs: Vec<&dyn KeyManager> = kms.iter().map(|km| km as &dyn KeyManager).collect();
+    kerl.rotate_with_threshold(&refs, &two_of_three, &two_of_three)?;
+    assert_eq!(kerl.get_state()?.unwrap().sn, 1);
+
+    Ok(())
+}
+
+#[cfg(feature = "secp256k1")]
+#[test]
+pub fn test_secp256k1_signature() -> Result<(), Error> {
+    use k256::ecdsa::{signature::Signer, Signature, SigningKey};
+
+    let signing_key = SigningKey::from_bytes(&[7u8; 32]).unwrap();
+    let key = BasicPrefix::new(
+        Basic::ECDSAsecp256k1,
+        PublicKey::new(signing_key.verify_key().to_bytes().to_vec()),
+    );
+    let message = b"message";
+    let signature: Signature = signing_key.sign(message);
+
+    assert_eq!(
+        key_prefix(PublicKey::new(signing_key.verify_key().to_bytes().to_vec()))?,
+        key
+    );
+    assert!(key_prefix(PublicKey::new(vec![0u8; 65])).is_err());
+    let code = signature_code(&key)?;
+    assert_eq!(code, SelfSigning::ECDSAsecp256k1Sha256);
+    let attached = AttachedSignaturePrefix::new(code, signature.as_ref().to_vec(), 0);
+    assert!(key.verify(message, &attached.signature)?);
+    assert!(!key.verify(b"other message", &attached.signature)?);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_anchor_digest() -> Result<(), Error> {
+    use keri::signer::CryptoBox;
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let km = CryptoBox::new()?;
+    let mut kerl = KERL::new(dir.path())?;
+    kerl.incept(&km)?;
+
+    let digest = SelfAddressing::Blake3_256.derive(b"document");
+    kerl.anchor_digest(digest.clone(), &km)?;
+
+    let ixn = kerl.get_event_at_sn(&kerl.get_prefix(), 1)?.unwrap();
+    match ixn.event.event_data {
+        EventData::Ixn(ixn) => assert!(matches!(
+            ixn.data.as_slice(),
+            [Seal::Digest(DigestSeal { dig })] if dig == &digest
+        )),
+        _ => panic!("Expected interaction event"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use keri::{error::Error as KeriError, keys::PublicKey, signer::CryptoBox, signer::KeyManager};
+
+use crate::error::Error;
+pub mod seeded;
+
+// Signer operating on raw bytes, so keys can be kept outside of the process
+// (e.g. in HSM or cloud KMS). Wrap it in `SignerAdapter` to use it as a key
+// manager of `Controller`.
+pub trait ExternalSigner: Send + Sync {
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>, Error>;
+    fn public_key(&self) -> Vec<u8>;
+    fn next_public_key(&self) -> Vec<u8>;
+    fn rotate(&mut self) -> Result<(), Error>;
+}
+
+pub struct SignerAdapter {
+    signer: Box<dyn ExternalSigner>,
+}
+
+impl SignerAdapter {
+    pub fn new<S: ExternalSigner + 'static>(signer: S) -> Self {
+        Self {
+            signer: Box::new(signer),
+        }
+    }
+}
+
+impl KeyManager for SignerAdapter {
+    fn sign(&self, msg: &Vec<u8>) -> Result<Vec<u8>, KeriError> {
+        self.signer
+            .sign(msg)
+            .map_err(|e| KeriError::SemanticError(e.to_string()))
+    }
+
+    fn public_key(&self) -> PublicKey {
+        PublicKey::new(self.signer.public_key())
+    }
+
+    fn next_public_key(&self) -> PublicKey {
+        PublicKey::new(self.signer.next_public_key())
+    }
+
+    fn rotate(&mut self) -> Result<(), KeriError> {
+        self.signer
+            .rotate()
+            .map_err(|e| KeriError::SemanticError(e.to_string()))
+    }
+}
+
+// Example of external signer backed by in memory keys.
+impl ExternalSigner for CryptoBox {
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        KeyManager::sign(self, &data.to_vec()).map_err(Error::KeyManagerError)
+    }
+
+    fn public_key(&self) -> Vec<u8> {
+        KeyManager::public_key(self).key()
+    }
+
+    fn next_public_key(&self) -> Vec<u8> {
+        KeyManager::next_public_key(self).key()
+    }
+
+    fn rotate(&mut self) -> Result<(), Error> {
+        KeyManager::rotate(self).map_err(Error::KeyManagerError)
+    }
+}
+
+#[test]
+pub fn test_signer_adapter() -> Result<(), Error> {
+    use crate::controller::{Controller, UpdateType};
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let mut km = SignerAdapter::new(CryptoBox::new()?);
+
+    let data = "message".as_bytes().to_vec();
+    let signature = km.sign(&data)?;
+    assert!(km.public_key().verify_ed(&data, &signature));
+
+    let next_key = km.next_public_key();
+    km.rotate()?;
+    assert_eq!(km.public_key().key(), next_key.key());
+
+    let controller = Controller::init(km, dir.path())?;
+    controller.update(UpdateType::Issue("vc".into()))?;
+
+    Ok(())
+}
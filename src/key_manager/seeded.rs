@@ -0,0 +1,140 @@
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{Aead, NewAead},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use ed25519_dalek::{Keypair, PublicKey as DalekPublicKey, SecretKey, Signer};
+use keri::{error::Error as KeriError, keys::PublicKey, signer::KeyManager};
+use rand::{rngs::OsRng, RngCore};
+
+use crate::error::Error;
+
+const SEED_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+// Key manager which can export its current and next seeds.
+pub trait ExportableKeyManager: KeyManager + Sized {
+    // Returns current and next seed.
+    fn seeds(&self) -> (Vec<u8>, Vec<u8>);
+    fn from_seeds(current: &[u8], next: &[u8]) -> Result<Self, Error>;
+}
+
+// Ed25519 key manager keeping keys generated from seeds.
+pub struct SeededKeyManager {
+    current: Keypair,
+    next: Keypair,
+}
+
+impl SeededKeyManager {
+    pub fn new() -> Self {
+        Self {
+            current: Keypair::generate(&mut OsRng),
+            next: Keypair::generate(&mut OsRng),
+        }
+    }
+
+    fn keypair_from_seed(seed: &[u8]) -> Result<Keypair, Error> {
+        let secret = SecretKey::from_bytes(seed)
+            .map_err(|_| Error::Generic("Improper seed length".into()))?;
+        let public = DalekPublicKey::from(&secret);
+        Ok(Keypair { secret, public })
+    }
+}
+
+impl Default for SeededKeyManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeyManager for SeededKeyManager {
+    fn sign(&self, msg: &Vec<u8>) -> Result<Vec<u8>, KeriError> {
+        Ok(self.current.sign(msg).to_bytes().to_vec())
+    }
+
+    fn public_key(&self) -> PublicKey {
+        PublicKey::new(self.current.public.to_bytes().to_vec())
+    }
+
+    fn next_public_key(&self) -> PublicKey {
+        PublicKey::new(self.next.public.to_bytes().to_vec())
+    }
+
+    fn rotate(&mut self) -> Result<(), KeriError> {
+        self.current = std::mem::replace(&mut self.next, Keypair::generate(&mut OsRng));
+        Ok(())
+    }
+}
+
+impl ExportableKeyManager for SeededKeyManager {
+    fn seeds(&self) -> (Vec<u8>, Vec<u8>) {
+        (
+            self.current.secret.to_bytes().to_vec(),
+            self.next.secret.to_bytes().to_vec(),
+        )
+    }
+
+    fn from_seeds(current: &[u8], next: &[u8]) -> Result<Self, Error> {
+        Ok(Self {
+            current: Self::keypair_from_seed(current)?,
+            next: Self::keypair_from_seed(next)?,
+        })
+    }
+}
+
+// Current and next seeds encrypted with XChaCha20Poly1305 under the key
+// derived from passphrase with Argon2.
+#[derive(Clone, Debug)]
+pub struct EncryptedSeed {
+    pub salt: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedSeed {
+    pub fn encrypt<K: ExportableKeyManager>(km: &K, passphrase: &str) -> Result<Self, Error> {
+        let (current, next) = km.seeds();
+        let mut salt = vec![0u8; SALT_LEN];
+        let mut nonce = vec![0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+
+        let key = derive_key(passphrase, &salt)?;
+        let ciphertext = XChaCha20Poly1305::new(Key::from_slice(&key))
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                [current, next].concat().as_ref(),
+            )
+            .map_err(|_| Error::Generic("Can't encrypt key material".into()))?;
+
+        Ok(Self {
+            salt,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    pub fn decrypt<K: ExportableKeyManager>(&self, passphrase: &str) -> Result<K, Error> {
+        if self.nonce.len() != NONCE_LEN {
+            return Err(Error::Generic("Improper nonce length".into()));
+        }
+        let key = derive_key(passphrase, &self.salt)?;
+        let seeds = XChaCha20Poly1305::new(Key::from_slice(&key))
+            .decrypt(XNonce::from_slice(&self.nonce), self.ciphertext.as_ref())
+            .map_err(|_| Error::Generic("Can't decrypt key material".into()))?;
+        if seeds.len() != 2 * SEED_LEN {
+            return Err(Error::Generic("Improper key material length".into()));
+        }
+        let (current, next) = seeds.split_at(SEED_LEN);
+        K::from_seeds(current, next)
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut key = vec![0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| Error::Generic(e.to_string()))?;
+    Ok(key)
+}
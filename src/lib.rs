@@ -1,6 +1,11 @@
 pub mod controller;
 pub mod error;
 pub mod kerl;
+pub mod key_manager;
+pub mod metrics;
+pub mod prefix;
 pub mod task;
 pub mod task_manager;
 pub mod tel;
+#[cfg(test)]
+mod test_support;
@@ -0,0 +1,64 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Counters of operations, shared by controller and task manager of
+// dispatcher. Updating them doesn't allocate nor lock.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    issued: AtomicU64,
+    revoked: AtomicU64,
+    verified: AtomicU64,
+    // Issuances, revocations and verifications which returned error, or
+    // verifications which returned false.
+    failed: AtomicU64,
+    // Tasks taken from queue by task manager.
+    dequeued: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Issued,
+    Revoked,
+    Verified,
+    Failed,
+    Dequeued,
+}
+
+// Values of counters at the moment of `Metrics::snapshot`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub issued: u64,
+    pub revoked: u64,
+    pub verified: u64,
+    pub failed: u64,
+    pub dequeued: u64,
+    // Tasks waiting in queue of task manager.
+    pub queue_depth: usize,
+}
+
+impl Metrics {
+    pub fn record(&self, operation: Operation) {
+        self.record_many(operation, 1)
+    }
+
+    pub fn record_many(&self, operation: Operation, count: u64) {
+        let counter = match operation {
+            Operation::Issued => &self.issued,
+            Operation::Revoked => &self.revoked,
+            Operation::Verified => &self.verified,
+            Operation::Failed => &self.failed,
+            Operation::Dequeued => &self.dequeued,
+        };
+        counter.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self, queue_depth: usize) -> MetricsSnapshot {
+        MetricsSnapshot {
+            issued: self.issued.load(Ordering::Relaxed),
+            revoked: self.revoked.load(Ordering::Relaxed),
+            verified: self.verified.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            dequeued: self.dequeued.load(Ordering::Relaxed),
+            queue_depth,
+        }
+    }
+}
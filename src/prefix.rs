@@ -0,0 +1,53 @@
+use keri::prefix::{IdentifierPrefix, SelfAddressingPrefix};
+
+use crate::error::Error;
+
+// Parses qualified identifier prefix, e.g. of issuer or registry.
+pub fn parse_identifier(s: &str) -> Result<IdentifierPrefix, Error> {
+    s.parse()
+        .map_err(|_| Error::Parse(format!("Invalid identifier: {}", s)))
+}
+
+// Parses self-addressing identifier, e.g. hash of vc.
+pub fn parse_said(s: &str) -> Result<SelfAddressingPrefix, Error> {
+    s.parse()
+        .map_err(|_| Error::Parse(format!("Invalid self-addressing identifier: {}", s)))
+}
+
+#[test]
+pub fn test_parse_identifier() -> Result<(), Error> {
+    use keri::{
+        derivation::basic::Basic,
+        prefix::Prefix,
+        signer::{CryptoBox, KeyManager},
+    };
+
+    let prefix = IdentifierPrefix::Basic(Basic::Ed25519.derive(CryptoBox::new()?.public_key()));
+    assert_eq!(parse_identifier(&prefix.to_str())?, prefix);
+
+    for invalid in &["", "not a prefix", &prefix.to_str()[..10]] {
+        assert!(matches!(parse_identifier(invalid), Err(Error::Parse(_))));
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn test_parse_said() -> Result<(), Error> {
+    use keri::{
+        derivation::{basic::Basic, self_addressing::SelfAddressing},
+        prefix::Prefix,
+        signer::{CryptoBox, KeyManager},
+    };
+
+    let said = SelfAddressing::Blake3_256.derive(b"vc");
+    assert_eq!(parse_said(&said.to_str())?, said);
+
+    // Valid identifier, which isn't self-addressing.
+    let basic = Basic::Ed25519.derive(CryptoBox::new()?.public_key());
+    for invalid in &["", "not a said", &basic.to_str()] {
+        assert!(matches!(parse_said(invalid), Err(Error::Parse(_))));
+    }
+
+    Ok(())
+}
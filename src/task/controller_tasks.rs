@@ -4,11 +4,11 @@ use std::{
 };
 
 use crate::error::Error;
-use keri::signer::KeyManager;
+use keri::{prefix::IdentifierPrefix, signer::KeyManager};
 
 use crate::controller::{Controller, MessageHash, UpdateType};
 
-use super::{HandleResult, Task};
+use super::{lock_poisoned, HandleResult, Task};
 
 #[derive(Debug)]
 pub struct IssueTask<K: KeyManager + Send + Sync + 'static> {
@@ -22,10 +22,10 @@ impl<K: KeyManager + Send + Sync + 'static> Task for IssueTask<K> {
     fn handle(&self) -> Result<HandleResult, Error> {
         let op_type = UpdateType::Issue(self.message.clone());
         let signature = {
-            let cont = self.controller.write().unwrap();
+            let cont = self.controller.read().map_err(lock_poisoned)?;
             cont.update(op_type)?;
 
-            cont.sign(&self.message.as_bytes().to_vec()).unwrap()
+            cont.sign(&self.message.as_bytes().to_vec())?
         };
         Ok(HandleResult::Issued(signature))
     }
@@ -42,7 +42,7 @@ impl<K: KeyManager + Send + Sync> IssueTask<K> {
 
 #[derive(Debug)]
 pub struct RevokeTask<K: KeyManager + Send + Sync + 'static> {
-    message_hash: MessageHash,
+    message_hash: String,
     controller: Arc<RwLock<Controller<K>>>,
 }
 
@@ -50,9 +50,12 @@ pub struct RevokeTask<K: KeyManager + Send + Sync + 'static> {
 
 impl<K: KeyManager + Send + Sync + 'static> Task for RevokeTask<K> {
     fn handle(&self) -> Result<HandleResult, Error> {
-        let op_type = UpdateType::Revoke(self.message_hash.clone());
+        let op_type = UpdateType::Revoke(self.message_hash.parse::<MessageHash>()?);
         {
-            self.controller.write().unwrap().update(op_type)?;
+            self.controller
+                .read()
+                .map_err(lock_poisoned)?
+                .update(op_type)?;
         }
         Ok(HandleResult::Revoked)
     }
@@ -61,7 +64,42 @@ impl<K: KeyManager + Send + Sync + 'static> Task for RevokeTask<K> {
 impl<K: KeyManager + Send + Sync> RevokeTask<K> {
     pub fn new(message_hash: String, controller: Arc<RwLock<Controller<K>>>) -> Self {
         RevokeTask {
-            message_hash: message_hash.parse().unwrap(),
+            message_hash,
+            controller,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct UpdateBackersTask<K: KeyManager + Send + Sync + 'static> {
+    added: Vec<IdentifierPrefix>,
+    removed: Vec<IdentifierPrefix>,
+    controller: Arc<RwLock<Controller<K>>>,
+}
+
+impl<K: KeyManager + Send + Sync + 'static> Task for UpdateBackersTask<K> {
+    fn handle(&self) -> Result<HandleResult, Error> {
+        // Issuance events are made from management tel state, so registry
+        // rotation waits for them to finish.
+        {
+            self.controller
+                .write()
+                .map_err(lock_poisoned)?
+                .update_backers(&self.added, &self.removed)?;
+        }
+        Ok(HandleResult::BackersUpdated)
+    }
+}
+
+impl<K: KeyManager + Send + Sync> UpdateBackersTask<K> {
+    pub fn new(
+        added: Vec<IdentifierPrefix>,
+        removed: Vec<IdentifierPrefix>,
+        controller: Arc<RwLock<Controller<K>>>,
+    ) -> Self {
+        UpdateBackersTask {
+            added,
+            removed,
             controller,
         }
     }
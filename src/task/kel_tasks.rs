@@ -4,7 +4,7 @@ use keri::signer::KeyManager;
 
 use crate::{controller::Controller, error::Error};
 
-use super::{HandleResult, Task};
+use super::{lock_poisoned, HandleResult, Task};
 
 #[derive(Debug)]
 pub struct GetKelTask<K: KeyManager + Send + Sync + 'static> {
@@ -13,10 +13,13 @@ pub struct GetKelTask<K: KeyManager + Send + Sync + 'static> {
 
 impl<K: KeyManager + Send + Sync + 'static> Task for GetKelTask<K> {
     fn handle(&self) -> Result<HandleResult, Error> {
-        Ok(match self.controller.read().unwrap().get_kerl() {
-            Ok(kel) => HandleResult::GotKel(kel.unwrap()),
-            Err(e) => HandleResult::Failure(e.to_string()),
-        })
+        let kel = self
+            .controller
+            .read()
+            .map_err(lock_poisoned)?
+            .get_kerl()?
+            .ok_or(Error::Generic("Empty KEL".into()))?;
+        Ok(HandleResult::GotKel(kel))
     }
 }
 
@@ -6,7 +6,7 @@ use std::{
 use crate::{controller::Controller, error::Error};
 use keri::signer::KeyManager;
 
-use super::{HandleResult, Task};
+use super::{lock_poisoned, HandleResult, Task};
 
 #[derive(Debug)]
 pub struct SignMessageTask<K: KeyManager + Send + Sync + 'static> {
@@ -19,7 +19,7 @@ impl<K: KeyManager + Send + Sync + 'static> Task for SignMessageTask<K> {
         let signature = self
             .controller
             .read()
-            .unwrap()
+            .map_err(lock_poisoned)?
             .sign(&self.message.clone())?;
         Ok(HandleResult::MessageSigned(signature))
     }
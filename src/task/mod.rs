@@ -1,6 +1,6 @@
-use crate::error::Error;
+use crate::{controller::signature::Signature, error::Error};
 use crossbeam_channel::Sender;
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::PoisonError};
 
 pub mod controller_tasks;
 pub mod kel_tasks;
@@ -11,6 +11,12 @@ pub trait Task {
     fn handle(&self) -> Result<HandleResult, Error>;
 }
 
+// Lock of controller is poisoned if a task panicked while holding it. Later
+// tasks fail with error instead of panicking too.
+pub(crate) fn lock_poisoned<T>(_: PoisonError<T>) -> Error {
+    Error::Generic("Controller lock is poisoned".into())
+}
+
 impl Debug for dyn Task {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{:?}", self)
@@ -22,31 +28,56 @@ pub trait DebugableTask: Task + Debug {}
 // #[derive(Debug)]
 pub struct AddressedTask {
     task: Box<dyn Task + Send + Sync>,
-    sender: Sender<HandleResult>,
+    sender: Sender<TaskResponse>,
+    request_id: u64,
 }
 
 impl AddressedTask {
-    pub fn new(task: Box<dyn Task + Send + Sync>, sender: Sender<HandleResult>) -> Self {
-        Self { task, sender }
+    pub fn new(
+        task: Box<dyn Task + Send + Sync>,
+        sender: Sender<TaskResponse>,
+        request_id: u64,
+    ) -> Self {
+        Self {
+            task,
+            sender,
+            request_id,
+        }
+    }
+
+    pub fn request_id(&self) -> u64 {
+        self.request_id
     }
 
+    // Errors of task are sent as `HandleResult::Failure`. Result is dropped
+    // if receiver is already gone.
     pub fn handle_and_send(&self) {
-        self.sender
-            .send(
-                self.task
-                    .handle()
-                    .unwrap_or_else(|e| HandleResult::Failure(e.to_string())),
-            )
-            .unwrap();
+        let result = self
+            .task
+            .handle()
+            .unwrap_or_else(|e| HandleResult::Failure(e.to_string()));
+        let _ = self.sender.send(TaskResponse {
+            request_id: self.request_id,
+            result,
+        });
     }
 }
 
+// Result of task together with id of request, returned when task was
+// pushed, so many requests can share one channel.
+#[derive(Debug)]
+pub struct TaskResponse {
+    pub request_id: u64,
+    pub result: HandleResult,
+}
+
 #[derive(Debug)]
 pub enum HandleResult {
-    GotTel(Vec<u8>),
+    GotTel(Vec<Vec<u8>>),
     GotKel(Vec<u8>),
-    Issued(Vec<u8>),
+    Issued(Signature),
     Revoked,
-    MessageSigned(Vec<u8>),
+    BackersUpdated,
+    MessageSigned(Signature),
     Failure(String),
 }
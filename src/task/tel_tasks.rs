@@ -2,7 +2,7 @@ use std::sync::{Arc, RwLock};
 
 use keri::signer::KeyManager;
 
-use super::{HandleResult, Task};
+use super::{lock_poisoned, HandleResult, Task};
 use crate::controller::{Controller, MessageHash};
 use crate::error::Error;
 
@@ -14,19 +14,15 @@ pub struct GetTelTask<K: KeyManager + Send + Sync + 'static> {
 
 impl<K: KeyManager + Send + Sync + 'static> Task for GetTelTask<K> {
     fn handle(&self) -> Result<HandleResult, Error> {
-        Ok(
-            match self
-                .controller
-                .read()
-                .unwrap()
-                .get_tel(self.message_hash.clone())
-            {
-                Ok(tel) => HandleResult::GotTel(tel),
-                Err(e) => HandleResult::Failure(e.to_string()),
-            },
-        )
+        let tel = self
+            .controller
+            .read()
+            .map_err(lock_poisoned)?
+            .get_tel(self.message_hash.clone())?;
+        Ok(HandleResult::GotTel(tel))
     }
 }
+
 impl<K: KeyManager + Send + Sync + 'static> GetTelTask<K> {
     pub fn new(controller: Arc<RwLock<Controller<K>>>, message_hash: MessageHash) -> Self {
         Self {
@@ -1,55 +1,225 @@
-use std::sync::Arc;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use crossbeam_channel::Sender;
 use crossbeam_queue::ArrayQueue;
+use log::{trace, warn};
+use rayon::ThreadPool;
 
 use crate::{
     error::Error,
-    task::{AddressedTask, HandleResult, Task},
+    metrics::{Metrics, MetricsSnapshot, Operation},
+    task::{AddressedTask, Task, TaskResponse},
 };
 
+const DEFAULT_WORKERS: usize = 3;
+// Time for which listener sleeps, when queue is empty.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
 pub struct TaskManager {
     queue: ArrayQueue<AddressedTask>,
+    // Number of tasks which can be handled at the same time.
+    workers: usize,
+    next_request_id: AtomicU64,
+    poll_interval: Duration,
+    metrics: Arc<Metrics>,
 }
 
 impl TaskManager {
-    pub fn new(n: usize) -> TaskManager {
+    pub fn new(n: usize, poll_interval: Duration) -> TaskManager {
+        Self {
+            poll_interval,
+            ..Self::with_workers(n, DEFAULT_WORKERS)
+        }
+    }
+
+    pub fn with_workers(n: usize, workers: usize) -> TaskManager {
         Self {
             queue: ArrayQueue::new(n),
+            workers,
+            next_request_id: AtomicU64::new(0),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            metrics: Arc::new(Metrics::default()),
         }
     }
 
+    // Sets counters updated by task manager, so they can be shared with
+    // controller.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    // Returns counters along with number of tasks waiting in queue.
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.metrics.snapshot(self.queue.len())
+    }
+
+    // Pushes task to queue. Returns id of request, which is attached to the
+    // task result.
     pub fn push(
         &self,
         task: Box<dyn Task + Send + Sync>,
-        sender: Sender<HandleResult>,
-    ) -> Result<(), Error> {
-        let at = AddressedTask::new(task, sender);
-        self.queue.push(at).map_err(|_at| Error::QueueError)
+        sender: Sender<TaskResponse>,
+    ) -> Result<u64, Error> {
+        let request_id = self.next_request_id.fetch_add(1, Ordering::SeqCst);
+        let at = AddressedTask::new(task, sender, request_id);
+        match self.queue.push(at) {
+            Ok(_) => {
+                trace!("Enqueued request {}", request_id);
+                Ok(request_id)
+            }
+            Err(_at) => {
+                warn!("Queue is full, request {} rejected", request_id);
+                Err(Error::QueueError)
+            }
+        }
     }
 
-    // Spawn thread which check if queue was updated.
+    // Spawn thread which check if queue was updated. Tasks are handled by
+    // pool of `workers` threads, so no more threads are started however many
+    // tasks are pushed.
     pub fn listen(tm: Arc<TaskManager>) -> Result<(), Error> {
         let pool = rayon::ThreadPoolBuilder::new()
-            .num_threads(3)
+            .num_threads(tm.workers)
             .build()
-            .unwrap();
+            .map_err(|e| Error::Generic(e.to_string()))?;
 
-        pool.spawn(move || loop {
-            tm.process_queue().unwrap();
+        std::thread::spawn(move || loop {
+            tm.process_queue(&pool);
         });
         Ok(())
     }
 
-    // Process task from queue if there is any.
-    fn process_queue(&self) -> Result<(), Error> {
-        let task = self.queue.pop();
-        if task.is_some() {
-            std::thread::spawn(move || {
-                task.unwrap().handle_and_send();
-            });
+    // Process task from queue if there is any. If queue was empty, sleeps
+    // for poll interval and returns false.
+    fn process_queue(&self, pool: &ThreadPool) -> bool {
+        match self.queue.pop() {
+            Some(task) => {
+                trace!("Dequeued request {}", task.request_id());
+                self.metrics.record(Operation::Dequeued);
+                pool.spawn(move || task.handle_and_send());
+                true
+            }
+            None => {
+                std::thread::sleep(self.poll_interval);
+                false
+            }
         }
+    }
+}
 
-        Ok(())
+#[test]
+pub fn test_bounded_workers() -> Result<(), Error> {
+    use crate::{task::HandleResult, test_support::CountingTask};
+    use crossbeam_channel::unbounded;
+
+    let workers = 2;
+    let tm = Arc::new(TaskManager::with_workers(20, workers));
+    let task = CountingTask::new(Duration::from_millis(20));
+    let (sender, receiver) = unbounded();
+    for _ in 0..10 {
+        tm.push(Box::new(task.clone()), sender.clone())?;
+    }
+    TaskManager::listen(Arc::clone(&tm))?;
+
+    for _ in 0..10 {
+        assert!(matches!(
+            receiver.recv().map(|r| r.result),
+            Ok(HandleResult::Revoked)
+        ));
+    }
+    let max_running = task.max_running();
+    assert!(max_running > 0 && max_running <= workers);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_task_flood() -> Result<(), Error> {
+    use crate::{task::HandleResult, test_support::CountingTask};
+    use crossbeam_channel::unbounded;
+
+    let workers = 4;
+    let tm = Arc::new(TaskManager::with_workers(100, workers));
+    TaskManager::listen(Arc::clone(&tm))?;
+    let task = CountingTask::new(Duration::from_millis(0));
+    let (sender, receiver) = unbounded();
+
+    let mut pushed = 0;
+    while pushed < 1000 {
+        match tm.push(Box::new(task.clone()), sender.clone()) {
+            Ok(_) => pushed += 1,
+            // Queue is full, wait for listener.
+            Err(Error::QueueError) => std::thread::yield_now(),
+            Err(e) => return Err(e),
+        }
     }
+
+    for _ in 0..1000 {
+        assert!(matches!(
+            receiver.recv().map(|r| r.result),
+            Ok(HandleResult::Revoked)
+        ));
+    }
+    assert!(task.max_running() <= workers);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_poll_interval() -> Result<(), Error> {
+    use crate::task::HandleResult;
+    use crossbeam_channel::unbounded;
+    use std::time::Instant;
+
+    struct NoopTask;
+
+    impl Task for NoopTask {
+        fn handle(&self) -> Result<HandleResult, Error> {
+            Ok(HandleResult::Revoked)
+        }
+    }
+
+    let poll_interval = Duration::from_millis(50);
+    let tm = TaskManager::new(5, poll_interval);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .map_err(|e| Error::Generic(e.to_string()))?;
+
+    // Empty queue is polled again only after poll interval.
+    let start = Instant::now();
+    assert!(!tm.process_queue(&pool));
+    assert!(start.elapsed() >= poll_interval);
+
+    // Task waiting in queue is taken without sleeping.
+    let (sender, receiver) = unbounded();
+    tm.push(Box::new(NoopTask), sender.clone())?;
+    let start = Instant::now();
+    assert!(tm.process_queue(&pool));
+    assert!(start.elapsed() < poll_interval);
+    assert!(matches!(
+        receiver.recv().map(|r| r.result),
+        Ok(HandleResult::Revoked)
+    ));
+
+    // Idle listener still handles tasks promptly.
+    let tm = Arc::new(TaskManager::new(5, DEFAULT_POLL_INTERVAL));
+    TaskManager::listen(Arc::clone(&tm))?;
+    std::thread::sleep(Duration::from_millis(20));
+    tm.push(Box::new(NoopTask), sender)?;
+    assert!(matches!(
+        receiver
+            .recv_timeout(Duration::from_secs(1))
+            .map(|r| r.result),
+        Ok(HandleResult::Revoked)
+    ));
+
+    Ok(())
 }
@@ -1,23 +1,63 @@
-use std::{fmt::Debug, path::Path};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    io::Write,
+    path::Path,
+    sync::RwLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use keri::{
     derivation::self_addressing::SelfAddressing,
+    event::SerializationFormats,
     prefix::{IdentifierPrefix, Prefix, SelfAddressingPrefix},
 };
 use teliox::{
     database::EventDatabase,
-    event::{manager_event::Config, verifiable_event::VerifiableEvent, Event},
+    event::{
+        manager_event::{Config, ManagerEventType, ManagerTelEvent},
+        vc_event::{VCEvent, VCEventType},
+        verifiable_event::VerifiableEvent,
+        Event,
+    },
     processor::EventProcessor,
     seal::EventSourceSeal,
     state::{vc_state::TelState, ManagerTelState, State},
     tel::event_generator,
 };
 
-use crate::error::Error;
+use crate::{controller::anchoring::AnchoringFailure, error::Error, kerl::KERL};
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+
+// Directory of issued vcs index, kept inside tel database directory.
+const ISSUED_INDEX_DIR: &str = "issued";
+// Tree of issued vcs index with source seals of re-anchoring KEL events.
+const REANCHORED_TREE: &str = "reanchored";
+// Tree of issued vcs index with tel events conflicting with stored ones.
+const FORKS_TREE: &str = "forks";
+// Directory of vcs metadata, kept inside tel database directory.
+const METADATA_DIR: &str = "metadata";
+// Tree of vcs metadata with datetimes of vc tel events.
+const DATETIMES_TREE: &str = "datetimes";
+// Tree of vcs metadata with reasons of revocation events.
+const REVOCATION_REASONS_TREE: &str = "revocation_reasons";
 
 pub struct Tel {
     tel_prefix: IdentifierPrefix,
     database: EventDatabase,
+    // Management tel state, updated with every processed management event.
+    management_state: RwLock<Option<ManagerTelState>>,
+    // States of vcs computed on query, by vc hash. Entry of vc is removed
+    // when its tel event is processed, or its fork is recorded or resolved.
+    vc_states: RwLock<HashMap<String, TelState>>,
+    // Hashes of issued vcs, in order of processing of issuance events.
+    issued: sled::Db,
+    // Local metadata of vcs. It isn't part of tel and isn't anchored, so it
+    // never affects verification.
+    metadata: sled::Db,
+    // Serialization format of generated tel events.
+    serialization: SerializationFormats,
 }
 
 impl Debug for Tel {
@@ -31,11 +71,38 @@ impl Tel {
         Ok(Self {
             database: Tel::create_tel_db(db_path)?,
             tel_prefix: IdentifierPrefix::default(),
+            management_state: RwLock::new(None),
+            vc_states: RwLock::new(HashMap::new()),
+            issued: open_index(&db_path.join(ISSUED_INDEX_DIR))?,
+            metadata: open_index(&db_path.join(METADATA_DIR))?,
+            serialization: SerializationFormats::JSON,
         })
     }
 
+    // Opens existing tel database of already incepted registry.
+    pub fn load(db_path: &Path, tel_prefix: IdentifierPrefix) -> Result<Self, Error> {
+        let tel = Self {
+            database: Tel::create_tel_db(db_path)?,
+            tel_prefix,
+            management_state: RwLock::new(None),
+            vc_states: RwLock::new(HashMap::new()),
+            issued: open_index(&db_path.join(ISSUED_INDEX_DIR))?,
+            metadata: open_index(&db_path.join(METADATA_DIR))?,
+            serialization: SerializationFormats::JSON,
+        };
+        tel.get_management_tel_state()?;
+        Ok(tel)
+    }
+
+    // Sets serialization format of events generated from now on. Already
+    // processed events keep their format.
+    pub fn with_serialization(mut self, format: SerializationFormats) -> Self {
+        self.serialization = format;
+        self
+    }
+
     fn create_tel_db(path: &Path) -> Result<EventDatabase, Error> {
-        EventDatabase::new(path).map_err(|e| e.into())
+        EventDatabase::new(path).map_err(|e| Error::database(path, e))
     }
 
     pub fn make_inception_event(
@@ -45,13 +112,14 @@ impl Tel {
         backer_threshold: u64,
         backers: Vec<IdentifierPrefix>,
     ) -> Result<Event, Error> {
+        validate_backer_threshold(&config, backer_threshold, &backers)?;
         event_generator::make_inception_event(
             issuer_prefix,
             config,
             backer_threshold,
             backers,
             None,
-            None,
+            Some(&self.serialization),
         )
         .map_err(|e| Error::from(e))
     }
@@ -61,18 +129,38 @@ impl Tel {
         ba: &[IdentifierPrefix],
         br: &[IdentifierPrefix],
     ) -> Result<Event, Error> {
-        event_generator::make_rotation_event(&self.get_management_tel_state()?, ba, br, None, None)
-            .map_err(|e| Error::from(e))
+        event_generator::make_rotation_event(
+            &self.get_management_tel_state()?,
+            ba,
+            br,
+            None,
+            Some(&self.serialization),
+        )
+        .map_err(|e| Error::from(e))
     }
 
-    pub fn make_issuance_event(&self, message: &str) -> Result<Event, Error> {
-        let derivation = SelfAddressing::Blake3_256;
-        let message_hash = derivation.derive(message.as_bytes());
+    // Vc can be issued only once. Known hashes are rejected, even if vc was
+    // revoked: vc state of teliox can't go back from revoked to issued, so
+    // there is no reissuance.
+    pub fn make_issuance_event(&self, message: &[u8]) -> Result<Event, Error> {
+        self.make_issuance_event_for(SelfAddressing::Blake3_256.derive(message))
+    }
+
+    // Makes issuance event of vc with already computed hash.
+    pub fn make_issuance_event_for(
+        &self,
+        message_hash: SelfAddressingPrefix,
+    ) -> Result<Event, Error> {
+        if self.contains(&message_hash)? {
+            return Err(Error::AlreadyIssued {
+                state: VcStatus::from(self.get_vc_state(&message_hash)?).state,
+            });
+        }
         event_generator::make_issuance_event(
             &self.get_management_tel_state()?,
             message_hash,
             None,
-            None,
+            Some(&self.serialization),
         )
         .map_err(|e| Error::from(e))
     }
@@ -82,14 +170,18 @@ impl Tel {
         let vc_state = self.get_vc_state(&message_hash)?;
         let last = match vc_state {
             TelState::Issued(last) => last,
-            _ => return Err(Error::Generic("Inproper vc state".into())),
+            state => {
+                return Err(Error::CannotRevoke {
+                    state: VcStatus::from(state).state,
+                })
+            }
         };
         event_generator::make_revoke_event(
             &message_hash,
             &last,
             &self.get_management_tel_state()?,
             None,
-            None,
+            Some(&self.serialization),
         )
         .map_err(|e| Error::from(e))
     }
@@ -105,22 +197,485 @@ impl Tel {
                 self.tel_prefix = man.prefix.to_owned()
             }
         }
+        self.update_management_state(&state);
         Ok(state)
     }
 
     // Process verifiable event (without mut). It doesn't check if source seal is correct. Just add event to tel.
     pub fn process(&self, event: Event, seal: EventSourceSeal) -> Result<State, Error> {
+        let (prefix, sn) = (event.get_prefix(), event.get_sn());
+        let issued_hash = match event.get_prefix() {
+            IdentifierPrefix::SelfAddressing(hash) if is_issuance(&event) => Some(hash),
+            _ => None,
+        };
+        let vc_hash = match (&event, event.get_prefix()) {
+            (Event::Vc(_), IdentifierPrefix::SelfAddressing(hash)) => Some(hash),
+            _ => None,
+        };
         let processor = EventProcessor::new(&self.database);
         let ve = VerifiableEvent::new(event, seal.into());
+        // Cache is locked while processing, so state of vc can't be cached
+        // from before the event.
+        let mut vc_states = self.vc_states.write().unwrap();
         let state = processor.process(ve)?;
+        if let Some(hash) = vc_hash {
+            vc_states.remove(&hash.to_str());
+        }
+        drop(vc_states);
+        trace!("Processed tel event of {} at sn {}", prefix.to_str(), sn);
+        self.update_management_state(&state);
+        if let Some(hash) = issued_hash {
+            // Ids are increasing, so index keeps order of issuance.
+            let id = self.issued.generate_id()?;
+            self.issued
+                .insert(id.to_be_bytes(), hash.to_str().as_bytes())?;
+        }
         Ok(state)
     }
 
+    // Parses stream of verifiable events and processes the ones which are
+    // anchored in `kerl`. Returns hashes of vcs which were updated. Events
+    // which couldn't be processed are reported in error, after processing
+    // the valid ones.
+    pub fn ingest(
+        &mut self,
+        stream: &[u8],
+        kerl: &KERL,
+    ) -> Result<Vec<SelfAddressingPrefix>, Error> {
+        let mut updated = vec![];
+        let mut rejected = vec![];
+        for event in parse_tel_stream(stream)? {
+            let (prefix, sn) = (event.event.get_prefix(), event.event.get_sn());
+            match self.record_fork(&event, kerl) {
+                Ok(true) => {
+                    if let IdentifierPrefix::SelfAddressing(message_hash) = prefix {
+                        if !updated.contains(&message_hash) {
+                            updated.push(message_hash)
+                        }
+                    }
+                    continue;
+                }
+                Ok(false) => (),
+                Err(e) => {
+                    warn!(
+                        "Rejected tel event of {} at sn {}: {}",
+                        prefix.to_str(),
+                        sn,
+                        e
+                    );
+                    rejected.push(format!("{} at sn {}: {}", prefix.to_str(), sn, e));
+                    continue;
+                }
+            }
+            match self.process_verified(event.event, event.seal.seal, kerl) {
+                Ok(State::Management(man)) => {
+                    if self.tel_prefix == IdentifierPrefix::default() {
+                        self.tel_prefix = man.prefix.to_owned();
+                        self.update_management_state(&State::Management(man));
+                    }
+                }
+                Ok(_) => {
+                    if let IdentifierPrefix::SelfAddressing(message_hash) = prefix {
+                        if !updated.contains(&message_hash) {
+                            updated.push(message_hash)
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!(
+                        "Rejected tel event of {} at sn {}: {}",
+                        prefix.to_str(),
+                        sn,
+                        e
+                    );
+                    rejected.push(format!("{} at sn {}: {}", prefix.to_str(), sn, e))
+                }
+            }
+        }
+        if rejected.is_empty() {
+            Ok(updated)
+        } else {
+            Err(Error::RejectedEvents(rejected))
+        }
+    }
+
+    // Ingests management tel exported with `get_management_events` and
+    // returns resulting registry state.
+    pub fn ingest_management(
+        &mut self,
+        stream: &[u8],
+        kerl: &KERL,
+    ) -> Result<ManagerTelState, Error> {
+        let is_management = parse_tel_stream(stream)?
+            .iter()
+            .all(|event| matches!(event.event, Event::Management(_)));
+        if !is_management {
+            return Err(Error::Generic("Stream contains vc events".into()));
+        }
+        self.ingest(stream, kerl)?;
+        self.refresh_state()
+    }
+
+    // Process verifiable event only if issuer's KEL contains event seal of it
+    // at the sn pointed by source seal.
+    pub fn process_verified(
+        &self,
+        event: Event,
+        seal: EventSourceSeal,
+        kerl: &KERL,
+    ) -> Result<State, Error> {
+        let expected_sn = self.next_sn(&event)?;
+        if event.get_sn() != expected_sn {
+            return Err(Error::Generic(format!(
+                "Unexpected tel event sn: {}, expected: {}",
+                event.get_sn(),
+                expected_sn
+            )));
+        }
+        let issuer = self.get_issuer_of(&event)?;
+        check_anchoring(&event, &seal, &issuer, kerl)?;
+        self.process(event, seal)
+    }
+
+    // Records vc event anchored in issuer's KEL, which conflicts with stored
+    // event at the same sn. Returns false if event doesn't fork vc tel.
+    fn record_fork(&self, event: &VerifiableEvent, kerl: &KERL) -> Result<bool, Error> {
+        let message_hash = match (&event.event, event.event.get_prefix()) {
+            (Event::Vc(_), IdentifierPrefix::SelfAddressing(hash)) => hash,
+            _ => return Ok(false),
+        };
+        let sn = event.event.get_sn();
+        let stored = match self.get_tel(&message_hash)?.into_iter().nth(sn as usize) {
+            Some(stored) => stored,
+            None => return Ok(false),
+        };
+        if canonical_bytes(&stored.event)? == canonical_bytes(&event.event)? {
+            return Ok(false);
+        }
+        let issuer = self.get_issuer_of(&event.event)?;
+        check_anchoring(&event.event, &event.seal.seal, &issuer, kerl)?;
+        self.issued
+            .open_tree(FORKS_TREE)?
+            .insert(fork_key(&message_hash, sn), event.serialize()?)?;
+        warn!("Recorded fork of vc {} at sn {}", message_hash.to_str(), sn);
+        self.vc_states
+            .write()
+            .unwrap()
+            .remove(&message_hash.to_str());
+        Ok(true)
+    }
+
+    // Returns conflicting events of vc tel at the lowest forked sn, if any.
+    pub fn detect_fork(&self, message_hash: &SelfAddressingPrefix) -> Result<Option<Fork>, Error> {
+        let (key, value) = match self
+            .issued
+            .open_tree(FORKS_TREE)?
+            .scan_prefix(message_hash.to_str().as_bytes())
+            .next()
+        {
+            Some(entry) => entry?,
+            None => return Ok(None),
+        };
+        let mut sn = [0u8; 8];
+        sn.copy_from_slice(&key[key.len() - 8..]);
+        let sn = u64::from_be_bytes(sn);
+        let stored = self
+            .get_tel(message_hash)?
+            .into_iter()
+            .nth(sn as usize)
+            .ok_or(Error::Generic("Missing forked tel event".into()))?;
+        let conflicting = parse_tel_stream(&value)?
+            .into_iter()
+            .next()
+            .ok_or(Error::Parse("Corrupted forked tel event".into()))?;
+        Ok(Some(Fork {
+            sn,
+            stored,
+            conflicting,
+        }))
+    }
+
+    // Resolves fork of vc tel in favour of stored event, if it's the
+    // preferred one. Otherwise stored tel can't be fixed in place and needs
+    // to be rebuilt from preferred events.
+    pub fn resolve_fork(&self, message_hash: &SelfAddressingPrefix) -> Result<(), Error> {
+        let fork = match self.detect_fork(message_hash)? {
+            Some(fork) => fork,
+            None => return Ok(()),
+        };
+        if !fork.is_stored_preferred()? {
+            return Err(Error::Forked { sn: fork.sn });
+        }
+        self.issued
+            .open_tree(FORKS_TREE)?
+            .remove(fork_key(message_hash, fork.sn))?;
+        self.vc_states
+            .write()
+            .unwrap()
+            .remove(&message_hash.to_str());
+        Ok(())
+    }
+
+    // Returns sn of next event of tel which given event belongs to, so tel
+    // events form a chain without gaps and duplicates, starting at 0.
+    fn next_sn(&self, event: &Event) -> Result<u64, Error> {
+        let prefix = event.get_prefix();
+        Ok(match event {
+            Event::Management(_) => {
+                match EventProcessor::new(&self.database).get_management_events(&prefix)? {
+                    Some(events) => parse_tel_stream(&events)?.len() as u64,
+                    None => 0,
+                }
+            }
+            Event::Vc(_) => self
+                .database
+                .get_events(&prefix)
+                .map(|events| events.count() as u64)
+                .unwrap_or(0),
+        })
+    }
+
+    // Returns issuer of registry, which is set in management inception event.
+    fn get_issuer_of(&self, event: &Event) -> Result<IdentifierPrefix, Error> {
+        match event {
+            Event::Management(ManagerTelEvent {
+                event_type: ManagerEventType::Vcp(vcp),
+                ..
+            }) => Ok(vcp.issuer_id.clone()),
+            _ => self.get_issuer(),
+        }
+    }
+
+    // Replaces cached management tel state with the one returned by
+    // processing management event of this tel.
+    fn update_management_state(&self, state: &State) {
+        if let State::Management(man) = state {
+            if man.prefix == self.tel_prefix {
+                *self.management_state.write().unwrap() = Some(man.clone());
+            }
+        }
+    }
+
+    // State of forked vc isn't definitive, so it's an error until fork is
+    // resolved. Computed state is cached until next event of the vc.
     pub fn get_vc_state(&self, message_hash: &SelfAddressingPrefix) -> Result<TelState, Error> {
+        let key = message_hash.to_str();
+        if let Some(state) = self.vc_states.read().unwrap().get(&key) {
+            return Ok(state.clone());
+        }
+        let mut vc_states = self.vc_states.write().unwrap();
+        if let Some(fork) = self.detect_fork(message_hash)? {
+            return Err(Error::Forked { sn: fork.sn });
+        }
         let message_prefix = IdentifierPrefix::SelfAddressing(message_hash.to_owned());
-        EventProcessor::new(&self.database)
-            .get_vc_state(&message_prefix)
-            .map_err(|e| Error::from(e))
+        let state = EventProcessor::new(&self.database).get_vc_state(&message_prefix)?;
+        vc_states.insert(key, state.clone());
+        Ok(state)
+    }
+
+    // Returns states of all vcs with given hashes, in order of hashes.
+    pub fn get_vc_states(
+        &self,
+        message_hashes: &[SelfAddressingPrefix],
+    ) -> Result<Vec<(SelfAddressingPrefix, TelState)>, Error> {
+        message_hashes
+            .iter()
+            .map(|message_hash| Ok((message_hash.to_owned(), self.get_vc_state(message_hash)?)))
+            .collect()
+    }
+
+    // Returns state of vc with sn of its last tel event.
+    pub fn get_vc_status(&self, message_hash: &SelfAddressingPrefix) -> Result<VcStatus, Error> {
+        let mut status = VcStatus::from(self.get_vc_state(message_hash)?);
+        if status.state != VcStatusKind::NotIssued {
+            status.sn = self
+                .get_tel(message_hash)?
+                .last()
+                .map(|event| event.event.get_sn());
+            status.local_issued_at = self.get_event_datetime(message_hash, 0)?;
+        }
+        if let (VcStatusKind::Revoked, Some(sn)) = (&status.state, status.sn) {
+            let reason = self
+                .metadata
+                .open_tree(REVOCATION_REASONS_TREE)?
+                .get(fork_key(message_hash, sn))?
+                .map(|reason| serde_json::from_slice(&reason))
+                .transpose()
+                .map_err(|e| Error::Parse(e.to_string()))?;
+            // Revocations made without reason are unspecified.
+            status.revocation_reason = Some(reason.unwrap_or(RevocationReason::Unspecified));
+        }
+        Ok(status)
+    }
+
+    // Returns reason of revocation of vc, or None if vc isn't revoked.
+    pub fn get_revocation_reason(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+    ) -> Result<Option<RevocationReason>, Error> {
+        Ok(self.get_vc_status(message_hash)?.revocation_reason)
+    }
+
+    // Records reason of revocation event of vc at `sn`. Revocation events of
+    // teliox have no attributes, so reason is kept in metadata, like event
+    // datetimes. It's local and unverifiable: it isn't anchored, isn't
+    // exported with tel and can be changed by anyone holding the database.
+    // Reason is reported only once revocation at `sn` is processed.
+    pub fn set_revocation_reason(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+        sn: u64,
+        reason: RevocationReason,
+    ) -> Result<(), Error> {
+        let reason = serde_json::to_vec(&reason).map_err(|e| Error::Generic(e.to_string()))?;
+        self.metadata
+            .open_tree(REVOCATION_REASONS_TREE)?
+            .insert(fork_key(message_hash, sn), reason)?;
+        Ok(())
+    }
+
+    // Checks if any tel events of vc with given hash are stored, to tell
+    // unknown vc apart from known one, which is not issued.
+    pub fn contains(&self, message_hash: &SelfAddressingPrefix) -> Result<bool, Error> {
+        let message_prefix = IdentifierPrefix::SelfAddressing(message_hash.to_owned());
+        Ok(self
+            .database
+            .get_events(&message_prefix)
+            .map(|mut events| events.next().is_some())
+            .unwrap_or(false))
+    }
+
+    // Records source seal of KEL event which re-anchored issuance event of
+    // vc, replacing previous one.
+    pub fn set_reanchored(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+        seal: &EventSourceSeal,
+    ) -> Result<(), Error> {
+        let value = [&seal.sn.to_be_bytes()[..], seal.digest.to_str().as_bytes()].concat();
+        self.issued
+            .open_tree(REANCHORED_TREE)?
+            .insert(message_hash.to_str().as_bytes(), value)?;
+        Ok(())
+    }
+
+    // Returns source seal of last KEL event which re-anchored issuance event
+    // of vc, if any.
+    pub fn get_reanchored(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+    ) -> Result<Option<EventSourceSeal>, Error> {
+        match self
+            .issued
+            .open_tree(REANCHORED_TREE)?
+            .get(message_hash.to_str().as_bytes())?
+        {
+            Some(value) if value.len() > 8 => {
+                let mut sn = [0u8; 8];
+                sn.copy_from_slice(&value[..8]);
+                Ok(Some(EventSourceSeal {
+                    sn: u64::from_be_bytes(sn),
+                    digest: String::from_utf8_lossy(&value[8..]).parse()?,
+                }))
+            }
+            Some(_) => Err(Error::Parse("Corrupted re-anchoring seal".into())),
+            None => Ok(None),
+        }
+    }
+
+    // Sets local metadata of known vc under given key, replacing previous
+    // value. Metadata is for issuer's bookkeeping only: it isn't anchored in
+    // KEL, isn't exported with tel and isn't used in verification.
+    pub fn set_metadata(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+        key: &str,
+        value: &[u8],
+    ) -> Result<(), Error> {
+        if !self.contains(message_hash)? {
+            return Err(Error::Generic("Unknown vc".into()));
+        }
+        self.metadata
+            .insert(metadata_key(message_hash, key), value)?;
+        Ok(())
+    }
+
+    pub fn get_metadata(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+        key: &str,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self
+            .metadata
+            .get(metadata_key(message_hash, key))?
+            .map(|value| value.to_vec()))
+    }
+
+    // Records ISO-8601 datetime of vc tel event at `sn`, as local and
+    // unverified metadata. Vc events of teliox have no `dt` field, so
+    // datetime is kept in metadata instead of event. Like other metadata, it
+    // isn't anchored and isn't exported with tel.
+    pub fn set_event_datetime(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+        sn: u64,
+        datetime: &str,
+    ) -> Result<(), Error> {
+        validate_datetime(datetime)?;
+        self.metadata
+            .open_tree(DATETIMES_TREE)?
+            .insert(fork_key(message_hash, sn), datetime.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn get_event_datetime(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+        sn: u64,
+    ) -> Result<Option<String>, Error> {
+        self.metadata
+            .open_tree(DATETIMES_TREE)?
+            .get(fork_key(message_hash, sn))?
+            .map(|datetime| {
+                String::from_utf8(datetime.to_vec()).map_err(|e| Error::Parse(e.to_string()))
+            })
+            .transpose()
+    }
+
+    // Flushes index of issued vcs and metadata to disk. Event database of
+    // teliox doesn't expose flush, sled flushes it in background and when
+    // it's dropped.
+    pub fn flush(&self) -> Result<(), Error> {
+        self.issued.flush()?;
+        self.metadata.flush()?;
+        Ok(())
+    }
+
+    // Returns hashes of all issued vcs, including revoked ones, in order of
+    // issuance.
+    pub fn list_issued(&self) -> Result<Vec<SelfAddressingPrefix>, Error> {
+        self.issued
+            .iter()
+            .values()
+            .map(|hash| parse_issued_hash(&hash?))
+            .collect()
+    }
+
+    // Returns page of `list_issued` starting at `offset`, and flag telling
+    // if there are more hashes after it. Index is read with cursor, so only
+    // entries up to the end of the page are visited.
+    pub fn list_issued_paged(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<SelfAddressingPrefix>, bool), Error> {
+        let mut page = self.issued.iter().values().skip(offset);
+        let hashes = page
+            .by_ref()
+            .take(limit)
+            .map(|hash| parse_issued_hash(&hash?))
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok((hashes, page.next().is_some()))
     }
 
     pub fn get_tel(
@@ -132,10 +687,77 @@ impl Tel {
             .map_err(|e| Error::from(e))
     }
 
+    // Returns sn of KEL event which anchored tel event of vc at `tel_sn`.
+    pub fn get_anchor_sn(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+        tel_sn: u64,
+    ) -> Result<u64, Error> {
+        self.get_tel(message_hash)?
+            .into_iter()
+            .find(|event| event.event.get_sn() == tel_sn)
+            .map(|event| event.seal.seal.sn)
+            .ok_or(Error::Generic(format!(
+                "Missing tel event at sn {}",
+                tel_sn
+            )))
+    }
+
+    // Iterates lazily over events of vc tel stored in database. Events are
+    // stored in order of processing, which follows sn order. Event with
+    // unexpected sn is returned as error.
+    pub fn iter_tel<'a>(
+        &'a self,
+        message_hash: &SelfAddressingPrefix,
+    ) -> impl Iterator<Item = Result<VerifiableEvent, Error>> + 'a {
+        let message_prefix = IdentifierPrefix::SelfAddressing(message_hash.to_owned());
+        let mut expected_sn = 0;
+        self.database
+            .get_events(&message_prefix)
+            .into_iter()
+            .flatten()
+            .map(move |event| {
+                let sn = event.event.get_sn();
+                if sn == expected_sn {
+                    expected_sn += 1;
+                    Ok(event)
+                } else {
+                    Err(Error::Generic(format!(
+                        "Unexpected tel event sn: {}, expected: {}",
+                        sn, expected_sn
+                    )))
+                }
+            })
+    }
+
+    // Writes serialized events of vc tel one by one into the writer.
+    pub fn write_tel<W: Write>(
+        &self,
+        message_hash: &SelfAddressingPrefix,
+        w: &mut W,
+    ) -> Result<(), Error> {
+        for event in self.get_tel(message_hash)? {
+            w.write_all(&event.serialize()?)?;
+        }
+        Ok(())
+    }
+
+    // Returns cached management tel state. It is computed from database only
+    // if cache is empty.
     pub fn get_management_tel_state(&self) -> Result<ManagerTelState, Error> {
-        EventProcessor::new(&self.database)
+        if let Some(state) = self.management_state.read().unwrap().as_ref() {
+            return Ok(state.clone());
+        }
+        self.refresh_state()
+    }
+
+    // Recomputes management tel state from database and replaces cached one.
+    pub fn refresh_state(&self) -> Result<ManagerTelState, Error> {
+        let state = EventProcessor::new(&self.database)
             .get_management_tel_state(&self.tel_prefix)
-            .map_err(|e| Error::from(e))
+            .map_err(|e| Error::from(e))?;
+        *self.management_state.write().unwrap() = Some(state.clone());
+        Ok(state)
     }
 
     pub fn get_management_events(&self) -> Result<Option<Vec<u8>>, Error> {
@@ -144,7 +766,690 @@ impl Tel {
             .map_err(|e| Error::from(e))
     }
 
+    // Returns inception event of registry, which sets its backers and
+    // configuration.
+    pub fn get_inception_event(&self) -> Result<Event, Error> {
+        let not_incepted = || Error::Generic("Registry is not incepted".into());
+        if self.tel_prefix == IdentifierPrefix::default() {
+            return Err(not_incepted());
+        }
+        let events = self.get_management_events()?.ok_or_else(not_incepted)?;
+        match parse_tel_stream(&events)?.into_iter().next() {
+            Some(VerifiableEvent {
+                event:
+                    event @ Event::Management(ManagerTelEvent {
+                        event_type: ManagerEventType::Vcp(_),
+                        ..
+                    }),
+                ..
+            }) => Ok(event),
+            _ => Err(not_incepted()),
+        }
+    }
+
     pub fn get_issuer(&self) -> Result<IdentifierPrefix, Error> {
         Ok(self.get_management_tel_state()?.issuer)
     }
 }
+
+// Serializable vc state, for API responses.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VcStatus {
+    pub state: VcStatusKind,
+    // Sn of last tel event of vc. Missing if vc wasn't issued.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sn: Option<u64>,
+    // Datetime of issuance recorded in local metadata, if any. It isn't part
+    // of the issuance event and isn't covered by any seal, so it can't be
+    // verified and can be changed by anyone holding the database.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_issued_at: Option<String>,
+    // Missing if vc isn't revoked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revocation_reason: Option<RevocationReason>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VcStatusKind {
+    NotIssued,
+    Issued,
+    Revoked,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevocationReason {
+    Unspecified,
+    KeyCompromise,
+    Superseded,
+    // Vc was issued by mistake.
+    Mistake,
+}
+
+impl Default for RevocationReason {
+    fn default() -> Self {
+        RevocationReason::Unspecified
+    }
+}
+
+// `TelState` doesn't keep sn of vc tel, so status made from it has no sn.
+impl From<TelState> for VcStatus {
+    fn from(state: TelState) -> Self {
+        let state = match state {
+            TelState::NotIsuued => VcStatusKind::NotIssued,
+            TelState::Issued(_) => VcStatusKind::Issued,
+            TelState::Revoked => VcStatusKind::Revoked,
+        };
+        VcStatus {
+            state,
+            sn: None,
+            local_issued_at: None,
+            revocation_reason: None,
+        }
+    }
+}
+
+// Conflicting events of vc tel at the same sn, both anchored in issuer's KEL.
+#[derive(Debug)]
+pub struct Fork {
+    pub sn: u64,
+    pub stored: VerifiableEvent,
+    pub conflicting: VerifiableEvent,
+}
+
+impl Fork {
+    // Event anchored earlier in issuer's KEL is preferred. Events anchored
+    // in the same KEL event are ordered by digest, so every replica picks
+    // the same one.
+    pub fn is_stored_preferred(&self) -> Result<bool, Error> {
+        let (stored_sn, conflicting_sn) = (self.stored.seal.seal.sn, self.conflicting.seal.seal.sn);
+        if stored_sn != conflicting_sn {
+            return Ok(stored_sn < conflicting_sn);
+        }
+        let digest = |event: &Event| -> Result<String, Error> {
+            Ok(SelfAddressing::Blake3_256
+                .derive(&canonical_bytes(event)?)
+                .to_str())
+        };
+        Ok(digest(&self.stored.event)? < digest(&self.conflicting.event)?)
+    }
+
+    pub fn preferred(&self) -> Result<&VerifiableEvent, Error> {
+        Ok(if self.is_stored_preferred()? {
+            &self.stored
+        } else {
+            &self.conflicting
+        })
+    }
+}
+
+// Checks if registry with given backers can satisfy backer threshold.
+fn validate_backer_threshold(
+    config: &[Config],
+    backer_threshold: u64,
+    backers: &[IdentifierPrefix],
+) -> Result<(), Error> {
+    if config.iter().any(|c| matches!(c, Config::NoBackers)) && backer_threshold != 0 {
+        return Err(Error::Generic(
+            "Registry without backers can't have backer threshold".into(),
+        ));
+    }
+    if backer_threshold > backers.len() as u64 {
+        return Err(Error::Generic(format!(
+            "Backer threshold {} is greater than number of backers {}",
+            backer_threshold,
+            backers.len()
+        )));
+    }
+    if !backers.is_empty() && backer_threshold == 0 {
+        return Err(Error::Generic(
+            "Backer threshold must be positive when backers are set".into(),
+        ));
+    }
+    Ok(())
+}
+
+// Returns bytes of tel event which are hashed into its event seal. Seals are
+// made and checked only over these bytes.
+pub fn canonical_bytes(event: &Event) -> Result<Vec<u8>, Error> {
+    Ok(event.serialize()?)
+}
+
+// Parses stream of serialized verifiable events.
+pub fn parse_tel_stream(stream: &[u8]) -> Result<Vec<VerifiableEvent>, Error> {
+    VerifiableEvent::parse(stream).map_err(|e| Error::Parse(e.to_string()))
+}
+
+// Checks if event is vc issuance event.
+pub fn is_issuance(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Vc(VCEvent {
+            event_type: VCEventType::Iss(_) | VCEventType::Bis(_),
+            ..
+        })
+    )
+}
+
+// Metadata is stored under hash of vc followed by key. Hashes have fixed
+// length for given derivation code, so keys of different vcs don't collide.
+fn metadata_key(message_hash: &SelfAddressingPrefix, key: &str) -> Vec<u8> {
+    [message_hash.to_str().as_bytes(), key.as_bytes()].concat()
+}
+
+// Checks if KEL event pointed by source seal has the seal's digest and
+// contains event seal of tel event.
+fn check_anchoring(
+    event: &Event,
+    seal: &EventSourceSeal,
+    issuer: &IdentifierPrefix,
+    kerl: &KERL,
+) -> Result<(), Error> {
+    if !kerl.check_source_seal(seal.sn, issuer, &seal.digest)? {
+        return Err(AnchoringFailure::SourceSealMismatch(seal.sn).into());
+    }
+    if !kerl.check_seal(seal.sn, issuer, event)? {
+        return Err(Error::Generic(
+            "Tel event is not anchored in issuer's KEL".into(),
+        ));
+    }
+    Ok(())
+}
+
+// Formats time as ISO-8601 UTC datetime with seconds precision, e.g.
+// `2021-07-01T12:00:00Z`.
+pub(crate) fn format_datetime(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or(0);
+    let (days, secs_of_day) = ((secs / 86400) as i64, secs % 86400);
+    // Civil date from days since epoch, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+// Checks if datetime is ISO-8601 date and time, with optional fraction of
+// second, and `Z` or offset, e.g. `2021-07-01T12:00:00.5+02:00`.
+pub(crate) fn validate_datetime(datetime: &str) -> Result<(), Error> {
+    let invalid = || Error::Parse(format!("Invalid datetime: {}", datetime));
+    let bytes = datetime.as_bytes();
+    let matches = |pattern: &[u8], bytes: &[u8]| {
+        pattern.len() == bytes.len()
+            && pattern.iter().zip(bytes).all(|(p, b)| match p {
+                b'0' => b.is_ascii_digit(),
+                _ => p == b,
+            })
+    };
+    if bytes.len() < 19 || !matches(b"0000-00-00T00:00:00", &bytes[..19]) {
+        return Err(invalid());
+    }
+    let mut rest = &bytes[19..];
+    if rest.first() == Some(&b'.') {
+        let digits = rest[1..].iter().take_while(|b| b.is_ascii_digit()).count();
+        if digits == 0 {
+            return Err(invalid());
+        }
+        rest = &rest[1 + digits..];
+    }
+    match rest {
+        b"Z" => Ok(()),
+        [b'+', offset @ ..] | [b'-', offset @ ..] if matches(b"00:00", offset) => Ok(()),
+        _ => Err(invalid()),
+    }
+}
+
+fn open_index(path: &Path) -> Result<sled::Db, Error> {
+    sled::open(path).map_err(|e| Error::database(path, e))
+}
+
+// Forks are stored under hash of vc followed by big endian sn, so they are
+// ordered by sn.
+fn fork_key(message_hash: &SelfAddressingPrefix, sn: u64) -> Vec<u8> {
+    [message_hash.to_str().as_bytes(), &sn.to_be_bytes()[..]].concat()
+}
+
+fn parse_issued_hash(hash: &[u8]) -> Result<SelfAddressingPrefix, Error> {
+    Ok(String::from_utf8_lossy(hash).parse()?)
+}
+
+// Checks if event is vc revocation event.
+pub fn is_revocation(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Vc(VCEvent {
+            event_type: VCEventType::Rev(_) | VCEventType::Brv(_),
+            ..
+        })
+    )
+}
+
+#[test]
+pub fn test_management_state_cache() -> Result<(), Error> {
+    use crate::test_support::fresh_tel;
+
+    let (tel, dummy_seal, _dir) = fresh_tel();
+
+    for i in 0..10 {
+        let iss = tel.make_issuance_event(i.to_string().as_bytes())?;
+        tel.process(iss, dummy_seal.clone())?;
+    }
+    // Cache was filled during inception and issuance didn't change it.
+    let cached = tel.management_state.read().unwrap().clone().unwrap();
+    let recomputed = tel.refresh_state()?;
+    assert_eq!(cached.prefix, recomputed.prefix);
+    assert_eq!(cached.sn, recomputed.sn);
+    assert_eq!(cached.last, recomputed.last);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_contains() -> Result<(), Error> {
+    use crate::test_support::fresh_tel;
+
+    let (tel, dummy_seal, _dir) = fresh_tel();
+
+    let iss = tel.make_issuance_event("vc".as_bytes())?;
+    tel.process(iss, dummy_seal)?;
+
+    let issued_hash = SelfAddressing::Blake3_256.derive("vc".as_bytes());
+    assert!(tel.contains(&issued_hash)?);
+
+    let random_hash = SelfAddressing::Blake3_256.derive("unknown vc".as_bytes());
+    assert!(!tel.contains(&random_hash)?);
+    assert!(matches!(
+        tel.get_vc_state(&random_hash)?,
+        TelState::NotIsuued
+    ));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_issue_known_hash() -> Result<(), Error> {
+    use crate::test_support::fresh_tel;
+
+    let (tel, dummy_seal, _dir) = fresh_tel();
+
+    let iss = tel.make_issuance_event("vc".as_bytes())?;
+    tel.process(iss, dummy_seal.clone())?;
+    assert!(matches!(
+        tel.make_issuance_event("vc".as_bytes()),
+        Err(Error::AlreadyIssued {
+            state: VcStatusKind::Issued
+        })
+    ));
+
+    let hash = SelfAddressing::Blake3_256.derive("vc".as_bytes());
+    let rev = tel.make_revoke_event(&hash.to_str())?;
+    tel.process(rev, dummy_seal)?;
+    assert!(matches!(
+        tel.make_issuance_event("vc".as_bytes()),
+        Err(Error::AlreadyIssued {
+            state: VcStatusKind::Revoked
+        })
+    ));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_get_vc_states() -> Result<(), Error> {
+    use crate::test_support::fresh_tel;
+
+    let (tel, dummy_seal, _dir) = fresh_tel();
+
+    for vc in &["issued", "revoked"] {
+        let iss = tel.make_issuance_event(vc.as_bytes())?;
+        tel.process(iss, dummy_seal.clone())?;
+    }
+    let revoked = SelfAddressing::Blake3_256.derive("revoked".as_bytes());
+    let rev = tel.make_revoke_event(&revoked.to_str())?;
+    tel.process(rev, dummy_seal)?;
+
+    let hashes: Vec<_> = ["issued", "revoked", "unknown"]
+        .iter()
+        .map(|vc| SelfAddressing::Blake3_256.derive(vc.as_bytes()))
+        .collect();
+    let states = tel.get_vc_states(&hashes)?;
+    assert_eq!(states.len(), 3);
+    assert!(states.iter().zip(&hashes).all(|((hash, _), h)| hash == h));
+    assert!(matches!(states[0].1, TelState::Issued(_)));
+    assert!(matches!(states[1].1, TelState::Revoked));
+    assert!(matches!(states[2].1, TelState::NotIsuued));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_backer_threshold() -> Result<(), Error> {
+    use keri::{derivation::basic::Basic, signer::CryptoBox, signer::KeyManager};
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let tel = Tel::new(dir.path())?;
+    let new_prefix = || -> Result<IdentifierPrefix, Error> {
+        Ok(IdentifierPrefix::Basic(
+            Basic::Ed25519.derive(CryptoBox::new()?.public_key()),
+        ))
+    };
+    let issuer = new_prefix()?;
+    let backers = vec![new_prefix()?, new_prefix()?];
+
+    // Threshold greater than number of backers.
+    assert!(tel
+        .make_inception_event(issuer.clone(), vec![], 3, backers.clone())
+        .is_err());
+    // Zero threshold with backers.
+    assert!(tel
+        .make_inception_event(issuer.clone(), vec![], 0, backers.clone())
+        .is_err());
+    // Threshold in registry without backers.
+    assert!(tel
+        .make_inception_event(issuer.clone(), vec![Config::NoBackers], 1, vec![])
+        .is_err());
+
+    tel.make_inception_event(issuer.clone(), vec![], 2, backers)?;
+    tel.make_inception_event(issuer, vec![Config::NoBackers], 0, vec![])?;
+
+    Ok(())
+}
+
+#[test]
+pub fn test_list_issued_paged() -> Result<(), Error> {
+    use crate::test_support::fresh_tel;
+
+    let (tel, dummy_seal, _dir) = fresh_tel();
+
+    let hashes: Vec<_> = (0..25)
+        .map(|i| SelfAddressing::Blake3_256.derive(i.to_string().as_bytes()))
+        .collect();
+    for i in 0..25 {
+        let iss = tel.make_issuance_event(i.to_string().as_bytes())?;
+        tel.process(iss, dummy_seal.clone())?;
+    }
+    // Revocation doesn't change the index.
+    let rev = tel.make_revoke_event(&hashes[3].to_str())?;
+    tel.process(rev, dummy_seal)?;
+    assert_eq!(tel.list_issued()?, hashes);
+
+    let (first, more) = tel.list_issued_paged(0, 10)?;
+    assert_eq!(first, hashes[..10].to_vec());
+    assert!(more);
+    let (second, more) = tel.list_issued_paged(10, 10)?;
+    assert_eq!(second, hashes[10..20].to_vec());
+    assert!(more);
+    let (last, more) = tel.list_issued_paged(20, 10)?;
+    assert_eq!(last, hashes[20..].to_vec());
+    assert!(!more);
+
+    // Pages are the same on next call.
+    assert_eq!(tel.list_issued_paged(10, 10)?.0, second);
+    assert_eq!(tel.list_issued_paged(25, 10)?, (vec![], false));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_vc_status() -> Result<(), Error> {
+    use crate::test_support::fresh_tel;
+
+    let (tel, dummy_seal, _dir) = fresh_tel();
+
+    let hash = SelfAddressing::Blake3_256.derive("vc".as_bytes());
+    let to_json = |status: VcStatus| serde_json::to_value(status).unwrap();
+    assert_eq!(
+        to_json(tel.get_vc_status(&hash)?),
+        serde_json::json!({ "state": "not_issued" })
+    );
+
+    let iss = tel.make_issuance_event("vc".as_bytes())?;
+    tel.process(iss, dummy_seal.clone())?;
+    assert_eq!(
+        to_json(tel.get_vc_status(&hash)?),
+        serde_json::json!({ "state": "issued", "sn": 0 })
+    );
+
+    let rev = tel.make_revoke_event(&hash.to_str())?;
+    tel.process(rev, dummy_seal)?;
+    assert_eq!(
+        to_json(tel.get_vc_status(&hash)?),
+        serde_json::json!({ "state": "revoked", "sn": 1, "revocation_reason": "unspecified" })
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn test_metadata() -> Result<(), Error> {
+    use crate::test_support::fresh_tel;
+
+    let hash = SelfAddressing::Blake3_256.derive("vc".as_bytes());
+    let (tel_prefix, dir) = {
+        let (tel, dummy_seal, dir) = fresh_tel();
+        assert!(tel.set_metadata(&hash, "label", b"diploma").is_err());
+
+        let iss = tel.make_issuance_event("vc".as_bytes())?;
+        tel.process(iss, dummy_seal)?;
+        let tel_before = tel.get_tel(&hash)?.len();
+        tel.set_metadata(&hash, "label", b"draft")?;
+        tel.set_metadata(&hash, "label", b"diploma")?;
+        tel.set_metadata(&hash, "ref", b"ext-42")?;
+        // Tel of vc is not changed.
+        assert_eq!(tel.get_tel(&hash)?.len(), tel_before);
+        (tel.get_management_tel_state()?.prefix, dir)
+    };
+
+    let tel = Tel::load(dir.path(), tel_prefix)?;
+    assert_eq!(tel.get_metadata(&hash, "label")?, Some(b"diploma".to_vec()));
+    assert_eq!(tel.get_metadata(&hash, "ref")?, Some(b"ext-42".to_vec()));
+    assert_eq!(tel.get_metadata(&hash, "missing")?, None);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_get_inception_event() -> Result<(), Error> {
+    use keri::{derivation::basic::Basic, signer::CryptoBox, signer::KeyManager};
+    use tempfile::tempdir;
+
+    let dir = tempdir().unwrap();
+    let new_prefix = || -> Result<IdentifierPrefix, Error> {
+        Ok(IdentifierPrefix::Basic(
+            Basic::Ed25519.derive(CryptoBox::new()?.public_key()),
+        ))
+    };
+    let dummy_seal = EventSourceSeal {
+        sn: 1,
+        digest: SelfAddressing::Blake3_256.derive("ixn".as_bytes()),
+    };
+    let mut tel = Tel::new(dir.path())?;
+    assert!(tel.get_inception_event().is_err());
+
+    let backers = vec![new_prefix()?, new_prefix()?];
+    let vcp = tel.make_inception_event(new_prefix()?, vec![], 2, backers.clone())?;
+    tel.incept_tel(vcp.clone(), dummy_seal.clone())?;
+    let rot = tel.make_rotation_event(&[new_prefix()?], &backers[..1])?;
+    tel.process(rot, dummy_seal)?;
+
+    let inception = tel.get_inception_event()?;
+    assert!(matches!(
+        inception,
+        Event::Management(ManagerTelEvent {
+            event_type: ManagerEventType::Vcp(_),
+            ..
+        })
+    ));
+    assert_eq!(canonical_bytes(&inception)?, canonical_bytes(&vcp)?);
+    let serialized = String::from_utf8(canonical_bytes(&inception)?).unwrap();
+    assert!(backers
+        .iter()
+        .all(|backer| serialized.contains(&backer.to_str())));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_revoke_not_issued() -> Result<(), Error> {
+    use crate::test_support::fresh_tel;
+
+    let (tel, dummy_seal, _dir) = fresh_tel();
+
+    let hash = SelfAddressing::Blake3_256.derive("vc".as_bytes());
+    assert!(matches!(
+        tel.make_revoke_event(&hash.to_str()),
+        Err(Error::CannotRevoke {
+            state: VcStatusKind::NotIssued
+        })
+    ));
+
+    let iss = tel.make_issuance_event("vc".as_bytes())?;
+    tel.process(iss, dummy_seal.clone())?;
+    let rev = tel.make_revoke_event(&hash.to_str())?;
+    tel.process(rev, dummy_seal)?;
+    assert!(matches!(
+        tel.make_revoke_event(&hash.to_str()),
+        Err(Error::CannotRevoke {
+            state: VcStatusKind::Revoked
+        })
+    ));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_vc_state_cache() -> Result<(), Error> {
+    use crate::test_support::fresh_tel;
+
+    let (tel, dummy_seal, _dir) = fresh_tel();
+    let is_cached =
+        |hash: &SelfAddressingPrefix| tel.vc_states.read().unwrap().contains_key(&hash.to_str());
+
+    let vc = SelfAddressing::Blake3_256.derive(b"vc");
+    tel.process(tel.make_issuance_event(b"vc")?, dummy_seal.clone())?;
+    assert!(!is_cached(&vc));
+    assert!(matches!(tel.get_vc_state(&vc)?, TelState::Issued(_)));
+    assert!(is_cached(&vc));
+    assert!(matches!(tel.get_vc_state(&vc)?, TelState::Issued(_)));
+
+    // Issuance of other vc keeps cached state.
+    let other_vc = SelfAddressing::Blake3_256.derive(b"other vc");
+    tel.process(tel.make_issuance_event(b"other vc")?, dummy_seal.clone())?;
+    assert!(is_cached(&vc));
+    assert!(matches!(tel.get_vc_state(&other_vc)?, TelState::Issued(_)));
+
+    // Revocation invalidates state of revoked vc only.
+    tel.process(tel.make_revoke_event(&vc.to_str())?, dummy_seal.clone())?;
+    assert!(!is_cached(&vc));
+    assert!(is_cached(&other_vc));
+    assert!(matches!(tel.get_vc_state(&vc)?, TelState::Revoked));
+    assert!(matches!(tel.get_vc_state(&other_vc)?, TelState::Issued(_)));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_event_datetime() -> Result<(), Error> {
+    use crate::test_support::fresh_tel;
+    use std::time::Duration;
+
+    for (secs, datetime) in &[
+        (0, "1970-01-01T00:00:00Z"),
+        (951782400, "2000-02-29T00:00:00Z"),
+        (1625140800, "2021-07-01T12:00:00Z"),
+        (4102444799, "2099-12-31T23:59:59Z"),
+    ] {
+        let time = UNIX_EPOCH + Duration::from_secs(*secs);
+        assert_eq!(format_datetime(time), *datetime);
+        assert!(validate_datetime(datetime).is_ok());
+    }
+    for invalid in &[
+        "",
+        "2021-07-01",
+        "2021-07-01T12:00:00",
+        "2021-07-01 12:00:00Z",
+        "2021-07-01T12:00:00.Z",
+        "2021-07-01T12:00:00+0200",
+    ] {
+        assert!(validate_datetime(invalid).is_err());
+    }
+
+    let hash = SelfAddressing::Blake3_256.derive("vc".as_bytes());
+    let (tel_prefix, dir) = {
+        let (tel, dummy_seal, dir) = fresh_tel();
+        tel.process(tel.make_issuance_event("vc".as_bytes())?, dummy_seal)?;
+        assert!(tel.set_event_datetime(&hash, 0, "yesterday").is_err());
+        tel.set_event_datetime(&hash, 0, "2021-07-01T12:00:00Z")?;
+        (tel.get_management_tel_state()?.prefix, dir)
+    };
+
+    // Datetime is kept in tel database.
+    let tel = Tel::load(dir.path(), tel_prefix)?;
+    assert_eq!(
+        tel.get_event_datetime(&hash, 0)?,
+        Some("2021-07-01T12:00:00Z".to_string())
+    );
+    assert_eq!(tel.get_event_datetime(&hash, 1)?, None);
+    assert_eq!(
+        tel.get_vc_status(&hash)?.local_issued_at,
+        Some("2021-07-01T12:00:00Z".to_string())
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn test_revocation_reason() -> Result<(), Error> {
+    use crate::test_support::fresh_tel;
+
+    let (tel, dummy_seal, _dir) = fresh_tel();
+
+    let (vc, other_vc) = (
+        SelfAddressing::Blake3_256.derive(b"vc"),
+        SelfAddressing::Blake3_256.derive(b"other vc"),
+    );
+    for message in &[&b"vc"[..], b"other vc"] {
+        tel.process(tel.make_issuance_event(message)?, dummy_seal.clone())?;
+    }
+    // Reason recorded ahead of revocation isn't reported yet.
+    tel.set_revocation_reason(&vc, 1, RevocationReason::KeyCompromise)?;
+    assert_eq!(tel.get_revocation_reason(&vc)?, None);
+
+    for hash in &[&vc, &other_vc] {
+        tel.process(tel.make_revoke_event(&hash.to_str())?, dummy_seal.clone())?;
+    }
+    assert_eq!(
+        tel.get_revocation_reason(&vc)?,
+        Some(RevocationReason::KeyCompromise)
+    );
+    assert_eq!(
+        serde_json::to_value(tel.get_vc_status(&vc)?).unwrap(),
+        serde_json::json!({ "state": "revoked", "sn": 1, "revocation_reason": "key_compromise" })
+    );
+    // Revocation without reason is unspecified.
+    assert_eq!(
+        tel.get_revocation_reason(&other_vc)?,
+        Some(RevocationReason::Unspecified)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,257 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use keri::{
+    derivation::{basic::Basic, self_addressing::SelfAddressing, self_signing::SelfSigning},
+    error::Error as KeriError,
+    event::sections::threshold::SignatureThreshold,
+    keys::PublicKey,
+    prefix::{AttachedSignaturePrefix, IdentifierPrefix},
+    signer::{CryptoBox, KeyManager},
+};
+use log::{Level, Log, Metadata, Record};
+use teliox::seal::EventSourceSeal;
+use tempfile::{tempdir, TempDir};
+
+use crate::{
+    controller::{resolve_db_paths, to_event_seal, to_source_seal, Controller, Dispatcher},
+    error::Error,
+    kerl::KERL,
+    task::{HandleResult, Task},
+    tel::Tel,
+};
+
+// Returns controller with new key manager and databases in temporary
+// directory, which is removed when returned guard is dropped.
+pub fn fresh_controller() -> (Controller<CryptoBox>, TempDir) {
+    let dir = tempdir().unwrap();
+    let controller = Controller::init(CryptoBox::new().unwrap(), dir.path()).unwrap();
+    (controller, dir)
+}
+
+// Returns controller of identifier with two keys and given signing
+// threshold, holding key manager of the first key, along with key manager of
+// the second one. Tel inception is anchored with signatures of both keys.
+pub fn fresh_multisig_controller(threshold: u64) -> (Controller<CryptoBox>, CryptoBox, TempDir) {
+    let dir = tempdir().unwrap();
+    let (kel_path, tel_path) = resolve_db_paths(dir.path());
+    let (km1, km2) = (CryptoBox::new().unwrap(), CryptoBox::new().unwrap());
+    let threshold = SignatureThreshold::Simple(threshold);
+    let mut kerl = KERL::new(&kel_path).unwrap();
+    kerl.incept_with_threshold(&[&km1, &km2], &threshold, &threshold)
+        .unwrap();
+    let mut tel = Tel::new(&tel_path).unwrap();
+    let vcp = tel
+        .make_inception_event(kerl.get_prefix(), vec![], 0, vec![])
+        .unwrap();
+    let ixn = kerl.make_ixn_seal(&[to_event_seal(&vcp).unwrap()]).unwrap();
+    let signatures = [&km1, &km2]
+        .iter()
+        .enumerate()
+        .map(|(i, km)| {
+            AttachedSignaturePrefix::new(
+                SelfSigning::Ed25519Sha512,
+                km.sign(&ixn.serialize().unwrap()).unwrap(),
+                i as u16,
+            )
+        })
+        .collect();
+    let ixn = kerl.process_with_signatures(&ixn, signatures).unwrap();
+    tel.incept_tel(vcp, to_source_seal(&ixn).unwrap()).unwrap();
+    let controller = Controller::from_parts(km1, Arc::new(kerl), Arc::new(tel), dir.path());
+    (controller, km2, dir)
+}
+
+pub fn fresh_dispatcher() -> (Dispatcher<CryptoBox>, TempDir) {
+    let dir = tempdir().unwrap();
+    let dispatcher = Dispatcher::init(CryptoBox::new().unwrap(), dir.path()).unwrap();
+    (dispatcher, dir)
+}
+
+// Returns tel with incepted registry of random issuer, in temporary
+// directory, and dummy seal to use when processing its events.
+pub fn fresh_tel() -> (Tel, EventSourceSeal, TempDir) {
+    let dir = tempdir().unwrap();
+    let issuer =
+        IdentifierPrefix::Basic(Basic::Ed25519.derive(CryptoBox::new().unwrap().public_key()));
+    let dummy_seal = EventSourceSeal {
+        sn: 1,
+        digest: SelfAddressing::Blake3_256.derive("ixn".as_bytes()),
+    };
+    let mut tel = Tel::new(dir.path()).unwrap();
+    let vcp = tel.make_inception_event(issuer, vec![], 0, vec![]).unwrap();
+    tel.incept_tel(vcp, dummy_seal.clone()).unwrap();
+    (tel, dummy_seal, dir)
+}
+
+// Key manager which starts failing to sign once `fail` flag is set.
+pub struct FailingKeyManager {
+    inner: CryptoBox,
+    fail: Arc<AtomicBool>,
+}
+
+impl FailingKeyManager {
+    pub fn new(fail: Arc<AtomicBool>) -> Self {
+        Self {
+            inner: CryptoBox::new().unwrap(),
+            fail,
+        }
+    }
+}
+
+impl KeyManager for FailingKeyManager {
+    fn sign(&self, msg: &Vec<u8>) -> Result<Vec<u8>, KeriError> {
+        if self.fail.load(Ordering::SeqCst) {
+            Err(KeriError::SemanticError("Signer unavailable".into()))
+        } else {
+            self.inner.sign(msg)
+        }
+    }
+
+    fn public_key(&self) -> PublicKey {
+        self.inner.public_key()
+    }
+
+    fn next_public_key(&self) -> PublicKey {
+        self.inner.next_public_key()
+    }
+
+    fn rotate(&mut self) -> Result<(), KeriError> {
+        self.inner.rotate()
+    }
+}
+
+// Key manager with secp256k1 keys, derived from consecutive seeds, so
+// rotated keys are predictable.
+#[cfg(feature = "secp256k1")]
+pub struct Secp256k1KeyManager {
+    seed: u8,
+    current: k256::ecdsa::SigningKey,
+    next: k256::ecdsa::SigningKey,
+}
+
+#[cfg(feature = "secp256k1")]
+impl Secp256k1KeyManager {
+    pub fn new() -> Self {
+        Self {
+            seed: 1,
+            current: Self::key(1),
+            next: Self::key(2),
+        }
+    }
+
+    fn key(seed: u8) -> k256::ecdsa::SigningKey {
+        k256::ecdsa::SigningKey::from_bytes(&[seed; 32]).unwrap()
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+impl KeyManager for Secp256k1KeyManager {
+    fn sign(&self, msg: &Vec<u8>) -> Result<Vec<u8>, KeriError> {
+        use k256::ecdsa::{signature::Signer, Signature};
+
+        let signature: Signature = self.current.sign(msg);
+        Ok(signature.as_ref().to_vec())
+    }
+
+    fn public_key(&self) -> PublicKey {
+        PublicKey::new(self.current.verify_key().to_bytes().to_vec())
+    }
+
+    fn next_public_key(&self) -> PublicKey {
+        PublicKey::new(self.next.verify_key().to_bytes().to_vec())
+    }
+
+    fn rotate(&mut self) -> Result<(), KeriError> {
+        self.seed += 1;
+        self.current = std::mem::replace(&mut self.next, Self::key(self.seed + 1));
+        Ok(())
+    }
+}
+
+// Task which records how many tasks are handled at the same time. Clones
+// share counters. Handling takes `duration`, or only yields if it's zero.
+#[derive(Clone)]
+pub struct CountingTask {
+    running: Arc<AtomicUsize>,
+    max_running: Arc<AtomicUsize>,
+    duration: Duration,
+}
+
+impl CountingTask {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            running: Arc::new(AtomicUsize::new(0)),
+            max_running: Arc::new(AtomicUsize::new(0)),
+            duration,
+        }
+    }
+
+    // Returns the most tasks handled at the same time so far.
+    pub fn max_running(&self) -> usize {
+        self.max_running.load(Ordering::SeqCst)
+    }
+}
+
+impl Task for CountingTask {
+    fn handle(&self) -> Result<HandleResult, Error> {
+        let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_running.fetch_max(running, Ordering::SeqCst);
+        if self.duration == Duration::from_millis(0) {
+            std::thread::yield_now();
+        } else {
+            std::thread::sleep(self.duration);
+        }
+        self.running.fetch_sub(1, Ordering::SeqCst);
+        Ok(HandleResult::Revoked)
+    }
+}
+
+// Logger which keeps records of all tests, since logger can be set only once
+// per process. Tests should look for records they expect, not count them.
+pub struct CapturingLogger {
+    records: Mutex<Vec<(Level, String)>>,
+}
+
+static LOGGER: CapturingLogger = CapturingLogger {
+    records: Mutex::new(Vec::new()),
+};
+
+impl Log for CapturingLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        self.records
+            .lock()
+            .unwrap()
+            .push((record.level(), record.args().to_string()));
+    }
+
+    fn flush(&self) {}
+}
+
+impl CapturingLogger {
+    // Checks if any record of given level contains `text`.
+    pub fn contains(&self, level: Level, text: &str) -> bool {
+        self.records
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(record_level, message)| *record_level == level && message.contains(text))
+    }
+}
+
+// Sets capturing logger, if it isn't set yet, and returns it.
+pub fn capture_logs() -> &'static CapturingLogger {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(log::LevelFilter::Trace);
+    }
+    &LOGGER
+}
@@ -1,8 +1,13 @@
 use std::sync::Arc;
 
 use crossbeam_channel::unbounded;
-use keri::{event::event_data::EventData, event_message::parse::Deserialized, signer::CryptoBox};
-use solid_adventure::{controller::Dispatcher, error::Error, task::HandleResult};
+use keri::signer::CryptoBox;
+use solid_adventure::{
+    controller::Dispatcher,
+    error::Error,
+    kerl::{event_kinds, EventKind},
+    task::HandleResult,
+};
 use tempfile::tempdir;
 
 #[test]
@@ -17,23 +22,15 @@ pub fn test_issuing() -> Result<(), Error> {
 
     let msg = "hi".to_string();
     controller.issue(msg, issuing_sender.clone())?;
-    let _recv = issuing_receiver.recv().unwrap();
+    let _recv = issuing_receiver.recv().unwrap().result;
 
     controller.get_kel(issuing_sender.clone())?;
-    match issuing_receiver.recv().unwrap() {
+    match issuing_receiver.recv().unwrap().result {
         HandleResult::GotKel(kel) => {
-            let parsed_kel = keri::event_message::parse::signed_event_stream(&kel)
-                .unwrap()
-                .1;
-            let mut ilks = parsed_kel.into_iter().map(|ev| match ev {
-                Deserialized::Event(e) => e.event.event.event.event_data,
-                Deserialized::NontransferableRct(_) => todo!(),
-                Deserialized::TransferableRct(_) => todo!(),
-            });
-            assert!(matches!(ilks.next(), Some(EventData::Icp(_))));
-            assert!(matches!(ilks.next(), Some(EventData::Ixn(_))));
-            assert!(matches!(ilks.next(), Some(EventData::Ixn(_))));
-            assert!(matches!(ilks.next(), None));
+            assert_eq!(
+                event_kinds(&kel)?,
+                vec![EventKind::Icp, EventKind::Ixn, EventKind::Ixn]
+            );
             Ok(())
         }
         _ => Err(Error::Generic("Wrong result type.".into())),
@@ -68,13 +65,16 @@ pub fn test_multithread_response() -> Result<(), Error> {
 
         std::thread::spawn(move || {
             assert!(matches!(
-                issuing_receiver.recv(),
+                issuing_receiver.recv().map(|r| r.result),
                 Ok(HandleResult::Issued(_))
             ));
         });
 
         std::thread::spawn(move || {
-            assert!(matches!(kel_receiver.recv(), Ok(HandleResult::GotKel(_))));
+            assert!(matches!(
+                kel_receiver.recv().map(|r| r.result),
+                Ok(HandleResult::GotKel(_))
+            ));
         });
 
         sender0.send(Arc::clone(&controller)).unwrap();